@@ -0,0 +1,268 @@
+use crate::emulator::{Emulator, PLAYER_BOTS};
+use crate::rng;
+use crate::scenario::Scenario;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+const WORST_SEEDS: usize = 5;
+
+pub struct Run {
+    pub seed: u32,
+    pub score: u64,
+    pub team_scores: Vec<u64>,
+    pub live_bots: usize,
+    pub live_drones: usize,
+}
+
+pub struct Stats {
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub stddev: f64,
+}
+
+impl Stats {
+    // all zeros without any values, e.g. a tournament entry whose every game was left out
+    pub fn of(values: &[f64]) -> Stats {
+        if values.is_empty() {
+            return Stats {
+                mean: 0.0,
+                median: 0.0,
+                min: 0.0,
+                max: 0.0,
+                stddev: 0.0,
+            };
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let count = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / count;
+        let variance = sorted.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / count;
+
+        let middle = sorted.len() / 2;
+        let median = match sorted.len() % 2 {
+            0 => (sorted[middle - 1] + sorted[middle]) / 2.0,
+            _ => sorted[middle],
+        };
+
+        Stats {
+            mean,
+            median,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+// "1-100", "3,7,42" or a mix of both like "1-10,42"
+pub fn parse_seeds(spec: &str) -> Result<Vec<u32>, String> {
+    let mut seeds = vec![];
+
+    for part in spec.split(',').map(str::trim) {
+        let parse = |seed: &str| {
+            seed.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("\"{}\" is not a seed", seed.trim()))
+        };
+
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    return Err(format!("the range {} is backwards", part));
+                }
+                seeds.extend(first..=last);
+            }
+            None => seeds.push(parse(part)?),
+        }
+    }
+
+    Ok(seeds)
+}
+
+/// Runs the programs once for every seed, spread over `jobs` threads, a seed that crashes the emulator fails the batch
+pub fn run(
+    programs: &[Vec<u16>],
+    drone_program: &[u16],
+    scenario: &Scenario,
+    iterations: u32,
+    seeds: &[u32],
+    jobs: usize,
+) -> Result<Vec<Run>, String> {
+    if scenario.record_rng.is_some() || scenario.replay_rng.is_some() {
+        return Err("batch runs can't record or replay the RNG".to_string());
+    }
+
+    // every thread grabs the next seed nobody has taken yet
+    let next = AtomicUsize::new(0);
+
+    let mut runs: Vec<(usize, Result<Run, String>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.clamp(1, seeds.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut runs = vec![];
+
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&seed) = seeds.get(index) else {
+                            break;
+                        };
+
+                        // one seed going wrong leaves the thread free for the others
+                        let run = panic::catch_unwind(AssertUnwindSafe(|| {
+                            run_seed(programs, drone_program, scenario, iterations, seed)
+                        }))
+                        .map_err(|error| {
+                            let message = match (error.downcast_ref::<&str>(), error.downcast_ref::<String>()) {
+                                (Some(message), _) => message.to_string(),
+                                (_, Some(message)) => message.clone(),
+                                _ => "unknown error".to_string(),
+                            };
+                            format!("the emulator crashed on seed {seed}: {message}")
+                        });
+                        runs.push((index, run));
+                    }
+
+                    runs
+                })
+            })
+            .collect();
+
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    });
+
+    // same order as the seeds were given in, no matter which thread finished first
+    runs.sort_by_key(|(index, _)| *index);
+
+    runs.into_iter().map(|(_, run)| run).collect()
+}
+
+fn run_seed(programs: &[Vec<u16>], drone_program: &[u16], scenario: &Scenario, iterations: u32, seed: u32) -> Run {
+    let rng = rng::from_scenario(scenario, seed).unwrap();
+    let mut emulator = Emulator::new(programs, drone_program, scenario, iterations, rng);
+
+    while emulator.current_tick < emulator.iterations {
+        emulator.tick();
+    }
+
+    let live = |players: bool| {
+        emulator
+            .bots
+            .iter()
+            .filter(|bot| (bot.id <= PLAYER_BOTS) == players && !bot.sleeping)
+            .count()
+    };
+
+    Run {
+        seed,
+        score: emulator.tank.score,
+        team_scores: emulator.tank.team_scores.clone(),
+        live_bots: live(true),
+        live_drones: live(false),
+    }
+}
+
+pub fn print_summary(runs: &[Run]) {
+    let stats = |value: fn(&Run) -> f64| Stats::of(&runs.iter().map(value).collect::<Vec<_>>());
+
+    println!("{:<12} {:>10} {:>10} {:>10} {:>10} {:>10}", "", "mean", "median", "min", "max", "stddev");
+    for (name, stats) in [
+        ("Score", stats(|run| run.score as f64)),
+        ("Live bots", stats(|run| run.live_bots as f64)),
+        ("Live drones", stats(|run| run.live_drones as f64)),
+    ] {
+        println!(
+            "{:<12} {:>10.1} {:>10.1} {:>10} {:>10} {:>10.1}",
+            name, stats.mean, stats.median, stats.min, stats.max, stats.stddev
+        );
+    }
+
+    let mut worst: Vec<&Run> = runs.iter().collect();
+    worst.sort_by_key(|run| (run.score, run.live_bots));
+
+    let worst = worst
+        .iter()
+        .take(WORST_SEEDS)
+        .map(|run| format!("{} (score {}, live bots {})", run.seed, run.score, run.live_bots))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("Worst seeds: {}", worst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Compiler;
+    use crate::emulator::DRONE_FIRMWARE;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parses_seed_lists_and_ranges() {
+        assert_eq!(parse_seeds("1-5").unwrap(), [1, 2, 3, 4, 5]);
+        assert_eq!(parse_seeds("3, 7,42").unwrap(), [3, 7, 42]);
+        assert_eq!(parse_seeds("1-3,10, 7 - 8").unwrap(), [1, 2, 3, 10, 7, 8]);
+        assert_eq!(parse_seeds("4-4").unwrap(), [4]);
+    }
+
+    #[test]
+    fn rejects_broken_seeds() {
+        assert_eq!(parse_seeds("5-1").unwrap_err(), "the range 5-1 is backwards");
+        assert_eq!(parse_seeds("1,x").unwrap_err(), "\"x\" is not a seed");
+        assert_eq!(parse_seeds("1-").unwrap_err(), "\"\" is not a seed");
+        assert!(parse_seeds("").is_err());
+        assert!(parse_seeds("-1").is_err());
+    }
+
+    #[test]
+    fn summarizes_values() {
+        let stats = Stats::of(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(stats.mean, 2.5);
+        assert_eq!(stats.median, 2.5);
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.max, 4.0);
+        assert!((stats.stddev - 1.25f64.sqrt()).abs() < 1e-12);
+
+        let stats = Stats::of(&[7.0, 1.0, 4.0]);
+        assert_eq!(stats.median, 4.0);
+
+        let stats = Stats::of(&[5.0]);
+        assert_eq!((stats.mean, stats.median, stats.stddev), (5.0, 5.0, 0.0));
+
+        let stats = Stats::of(&[]);
+        assert_eq!((stats.mean, stats.min, stats.max), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn reports_the_first_seed_that_crashes() {
+        // more than fits into memory, flashing it panics
+        let programs = [vec![0; 3601]];
+        let error = run(&programs, &DRONE_FIRMWARE, &Scenario::default(), 10, &[4, 2], 2).err().unwrap();
+        assert!(error.starts_with("the emulator crashed on seed 4: "), "{error}");
+    }
+
+    #[test]
+    fn runs_match_single_runs_in_seed_order() {
+        let compiler = Compiler::load(&PathBuf::from("bots/samplebot.asm"), false, false).unwrap();
+        let programs = [compiler.output];
+        let scenario = Scenario::default();
+        let seeds = [9, 2, 5, 1];
+
+        let runs = run(&programs, &DRONE_FIRMWARE, &scenario, 5000, &seeds, 3).unwrap();
+
+        assert_eq!(runs.iter().map(|run| run.seed).collect::<Vec<_>>(), seeds);
+        for run in runs {
+            let rng = rng::from_scenario(&scenario, run.seed).unwrap();
+            let mut emulator = Emulator::new(&programs, &DRONE_FIRMWARE, &scenario, 5000, rng);
+            while emulator.current_tick < emulator.iterations {
+                emulator.tick();
+            }
+            assert_eq!(run.score, emulator.tank.score);
+            assert_eq!(run.team_scores, emulator.tank.team_scores);
+        }
+    }
+}
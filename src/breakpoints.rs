@@ -0,0 +1,317 @@
+use crate::emulator::{Bot, Emulator, PLAYER_BOTS};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub enum Condition {
+    Address(u16),
+    Memory(u16),
+    Register(usize),
+    StackPointer,
+    EnergyBelow(u16),
+    EnergyAbove(u16),
+    Flag(char),
+}
+
+impl Condition {
+    fn value(&self, bot: &Bot) -> u16 {
+        match self {
+            Condition::Address(_) => bot.instruction_pointer,
+            Condition::Memory(address) => bot.program_memory[*address as usize],
+            Condition::Register(reg) => bot.registers[*reg],
+            Condition::StackPointer => bot.stack_pointer,
+            Condition::EnergyBelow(_) | Condition::EnergyAbove(_) => bot.energy,
+            Condition::Flag(flag) => {
+                let set = match flag {
+                    's' => bot.flags.success,
+                    'e' => bot.flags.equal,
+                    'l' => bot.flags.less,
+                    'g' => bot.flags.greater,
+                    _ => false,
+                };
+                set as u16
+            }
+        }
+    }
+
+    fn is_triggered(&self, before: u16, after: u16) -> bool {
+        match self {
+            // only when the bot gets there, not for every tick it spends there
+            Condition::Address(address) => before != *address && after == *address,
+            Condition::EnergyBelow(threshold) => before >= *threshold && after < *threshold,
+            Condition::EnergyAbove(threshold) => before <= *threshold && after > *threshold,
+            _ => before != after,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Breakpoint {
+    pub spec: String,
+    pub bot_id: Option<u16>,
+    pub team: usize, // watched by points without a bot, drones and other teams are left alone
+    pub condition: Condition,
+}
+
+impl Breakpoint {
+    /// Parses `[BOT:]ADDR|LABEL`, labels are looked up in the symbol table of the bot's team,
+    /// or of `team` without a bot
+    pub fn parse_breakpoint(
+        spec: &str,
+        symbol_tables: &[HashMap<String, u16>],
+        player_bots: u16,
+        team: usize,
+    ) -> Result<Breakpoint, String> {
+        let (bot_id, target) = Self::split_bot(spec)?;
+        let address = Self::parse_address(target, Self::labels_for(bot_id, team, symbol_tables, player_bots))?;
+
+        Ok(Breakpoint {
+            spec: spec.to_string(),
+            bot_id,
+            team,
+            condition: Condition::Address(address - address % 3),
+        })
+    }
+
+    /// Parses `[BOT:]TARGET` where target is one of `[ADDR|LABEL]`, `rN`, `sp`,
+    /// `energy<N`, `energy>N` or `flag:F`
+    pub fn parse_watchpoint(
+        spec: &str,
+        symbol_tables: &[HashMap<String, u16>],
+        player_bots: u16,
+        team: usize,
+    ) -> Result<Breakpoint, String> {
+        let (bot_id, target) = Self::split_bot(spec)?;
+        let labels = Self::labels_for(bot_id, team, symbol_tables, player_bots);
+        let target = target.to_lowercase();
+
+        let condition = if let Some(address) = target.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            Condition::Memory(Self::parse_address(address, labels)?)
+        } else if target == "sp" {
+            Condition::StackPointer
+        } else if let Some(threshold) = target.strip_prefix("energy<") {
+            Condition::EnergyBelow(Self::parse_number(threshold)?)
+        } else if let Some(threshold) = target.strip_prefix("energy>") {
+            Condition::EnergyAbove(Self::parse_number(threshold)?)
+        } else if let Some(flag) = target.strip_prefix("flag:") {
+            match flag {
+                "s" | "e" | "l" | "g" => Condition::Flag(flag.chars().next().unwrap()),
+                _ => return Err(format!("Unknown flag \"{flag}\", expected one of s, e, l, g")),
+            }
+        } else if let Some(reg) = target.strip_prefix('r') {
+            match reg.parse::<usize>() {
+                Ok(reg) if reg <= 13 => Condition::Register(reg),
+                _ => return Err(format!("Unknown register \"{target}\"")),
+            }
+        } else {
+            return Err(format!("Unknown watch target \"{target}\""));
+        };
+
+        Ok(Breakpoint {
+            spec: spec.to_string(),
+            bot_id,
+            team,
+            condition,
+        })
+    }
+
+    // breakpoints without a bot use the labels of `team`, drones have none
+    fn labels_for(
+        bot_id: Option<u16>,
+        team: usize,
+        symbol_tables: &[HashMap<String, u16>],
+        player_bots: u16,
+    ) -> Option<&HashMap<String, u16>> {
+        match bot_id {
+            Some(id) if id > player_bots => None,
+            Some(id) => symbol_tables.get(Emulator::team_of(id, symbol_tables.len(), player_bots)),
+            None => symbol_tables.get(team),
+        }
+    }
+
+    fn split_bot(spec: &str) -> Result<(Option<u16>, &str), String> {
+        if let Some((bot, target)) = spec.split_once(':') {
+            if let Some(id) = Self::parse_bot(bot) {
+                return Ok((Some(id), target));
+            } else if bot.len() == 1 || bot.starts_with('@') {
+                return Err(format!("Invalid bot identifier \"{bot}\""));
+            }
+        }
+
+        Ok((None, spec))
+    }
+
+    // accepts the same names as Bot::get_name
+    fn parse_bot(name: &str) -> Option<u16> {
+        if let Some(drone) = name.strip_prefix('@') {
+            return match drone.parse::<u16>() {
+                Ok(drone) if drone > 0 => Some(drone + PLAYER_BOTS),
+                _ => None,
+            };
+        }
+
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(glyph @ ('A'..='Z' | 'a'..='x')), None) => Some(Bot::id_from_glyph(glyph)),
+            _ => None,
+        }
+    }
+
+    fn parse_number(number: &str) -> Result<u16, String> {
+        let result = match number.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => number.parse(),
+        };
+
+        result.map_err(|_| format!("Invalid number \"{number}\""))
+    }
+
+    fn watches(&self, bot: &Bot) -> bool {
+        match self.bot_id {
+            Some(id) => id == bot.id,
+            None => bot.team == Some(self.team),
+        }
+    }
+
+    fn parse_address(address: &str, labels: Option<&HashMap<String, u16>>) -> Result<u16, String> {
+        let address = match labels.and_then(|labels| labels.get(&address.to_lowercase())) {
+            Some(address) => *address,
+            None => match Self::parse_number(address) {
+                Ok(address) => address,
+                Err(_) => return Err(format!("Unknown label or address \"{address}\"")),
+            },
+        };
+
+        if address >= 3600 {
+            return Err(format!("Address {address} is out of range"));
+        }
+
+        Ok(address)
+    }
+}
+
+pub struct Hit {
+    pub bot_id: u16,
+    pub description: String,
+}
+
+/// Checks breakpoints and watchpoints between ticks
+pub struct Breakpoints {
+    points: Vec<Breakpoint>,
+    previous: Vec<Vec<u16>>,
+}
+
+impl Breakpoints {
+    pub fn new(points: Vec<Breakpoint>) -> Breakpoints {
+        Breakpoints {
+            points,
+            previous: vec![],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn has_breakpoint(&self, bot: &Bot, address: u16) -> bool {
+        self.points
+            .iter()
+            .any(|point| matches!(point.condition, Condition::Address(at) if at == address) && point.watches(bot))
+    }
+
+    /// Returns the first breakpoint hit since the previous check
+    pub fn check(&mut self, bots: &[Bot]) -> Option<Hit> {
+        let first_check = self.previous.is_empty();
+        if first_check {
+            self.previous = self.points.iter().map(|_| vec![0; bots.len()]).collect();
+        }
+
+        let mut hit = None;
+
+        for (point, previous) in self.points.iter().zip(self.previous.iter_mut()) {
+            for (bot, before) in bots.iter().zip(previous.iter_mut()) {
+                let after = point.condition.value(bot);
+
+                if point.watches(bot) && !first_check && hit.is_none() && point.condition.is_triggered(*before, after) {
+                    let description = match point.condition {
+                        Condition::Address(_) => format!("breakpoint {} hit by {}", point.spec, bot.get_name()),
+                        _ => format!("watchpoint {} triggered by {} ({} -> {})", point.spec, bot.get_name(), before, after),
+                    };
+
+                    hit = Some(Hit {
+                        bot_id: bot.id,
+                        description,
+                    });
+                }
+
+                *before = after;
+            }
+        }
+
+        hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Position;
+
+    fn bot(id: u16, team: Option<usize>) -> Bot {
+        Bot::new(id, team, Position { x: 0, y: 0, z: 0 }, 1000)
+    }
+
+    fn breakpoints(specs: &[&str], team: usize) -> Breakpoints {
+        let symbol_tables = [HashMap::from([("loop".to_string(), 6)]), HashMap::from([("loop".to_string(), 9)])];
+        let points = specs
+            .iter()
+            .map(|spec| Breakpoint::parse_breakpoint(spec, &symbol_tables, 50, team).unwrap())
+            .collect();
+        Breakpoints::new(points)
+    }
+
+    #[test]
+    fn address_breakpoints_fire_when_the_bot_gets_there() {
+        let mut breakpoints = breakpoints(&["loop"], 0);
+        let mut bots = vec![bot(1, Some(0))];
+        assert!(breakpoints.check(&bots).is_none());
+
+        bots[0].instruction_pointer = 6;
+        assert_eq!(breakpoints.check(&bots).unwrap().bot_id, 1);
+        // still there on the next tick, e.g. sleeping or jumping to itself
+        assert!(breakpoints.check(&bots).is_none());
+
+        bots[0].instruction_pointer = 9;
+        assert!(breakpoints.check(&bots).is_none());
+        bots[0].instruction_pointer = 6;
+        assert!(breakpoints.check(&bots).is_some());
+    }
+
+    #[test]
+    fn points_without_a_bot_only_watch_their_team() {
+        // the second team's labels, its bots are 26-50
+        let mut breakpoints = breakpoints(&["loop"], 1);
+        let mut bots = vec![bot(1, Some(0)), bot(30, Some(1)), bot(51, None)];
+        breakpoints.check(&bots);
+
+        for bot in &mut bots {
+            bot.instruction_pointer = 9;
+        }
+        assert_eq!(breakpoints.check(&bots).unwrap().bot_id, 30);
+
+        assert!(!breakpoints.has_breakpoint(&bots[0], 9));
+        assert!(breakpoints.has_breakpoint(&bots[1], 9));
+        assert!(!breakpoints.has_breakpoint(&bots[2], 9));
+    }
+
+    #[test]
+    fn points_with_a_bot_only_watch_that_bot() {
+        let mut breakpoints = breakpoints(&["@1:0x9"], 0);
+        let mut bots = vec![bot(1, Some(0)), bot(51, None), bot(52, None)];
+        breakpoints.check(&bots);
+
+        for bot in &mut bots {
+            bot.instruction_pointer = 9;
+        }
+        assert_eq!(breakpoints.check(&bots).unwrap().bot_id, 51);
+    }
+}
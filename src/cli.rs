@@ -1,12 +1,13 @@
+use crate::scenario::RngKind;
 use clap::{Parser, ValueHint};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None, arg_required_else_help = true)]
 pub struct Arguments {
-    /// Specify the player's organism source file
-    #[arg(value_name="BOT", value_hint = ValueHint::FilePath)]
-    pub bot_path: PathBuf,
+    /// Specify the player's organism source file or firmware, pass several to have them compete as teams
+    #[arg(value_name="BOT", value_hint = ValueHint::FilePath, required_unless_present_any = ["tournament_dir", "replay_path"], num_args = 1..)]
+    pub bot_paths: Vec<PathBuf>,
 
     /// This allows you to use the old argument format
     #[arg(short = 'p', hide = true)]
@@ -20,18 +21,118 @@ pub struct Arguments {
     #[arg(short = 'g', long = None, value_name="CHAR")]
     pub debug_bot: Option<char>,
 
+    /// Pause when an organism reaches an address or label, e.g. "A:loop", or "120" for any organism of the debugged team
+    #[arg(short = 'b', long = "break", value_name="[BOT:]ADDR")]
+    pub breakpoints: Vec<String>,
+
+    /// Pause when a value changes: "[ADDR]", "rN", "sp", "energy<N", "energy>N" or "flag:F", without BOT for the debugged team
+    #[arg(short = 'w', long = "watch", value_name="[BOT:]TARGET")]
+    pub watchpoints: Vec<String>,
+
+    /// Replace the stock drone firmware with an organism source file or .bin firmware
+    #[arg(short = 'd', long = "drone", value_name="PATH", value_hint = ValueHint::FilePath)]
+    pub drone_path: Option<PathBuf>,
+
+    /// Load the tank layout and game rules from a TOML or JSON scenario file
+    #[arg(long = "scenario", value_name="PATH", value_hint = ValueHint::FilePath)]
+    pub scenario_path: Option<PathBuf>,
+
+    /// Specify # of drones [default: 20]
+    #[arg(long = "drones", value_name="NUM", value_parser = clap::value_parser!(u16).range(0..=500))]
+    pub drone_count: Option<u16>,
+
+    /// Enable the extended instruction set (GETID, LEVEL, CLIMB)
+    #[arg(short = 'x', long = "extended", default_value_t = false)]
+    pub extended: bool,
+
+    /// Specify # of tank levels, linked by ramps that organisms can CLIMB [default: 1]
+    #[arg(long = "levels", value_name="NUM", value_parser = clap::value_parser!(u8).range(1..=8))]
+    pub levels: Option<u8>,
+
+    /// Show every tank level side by side instead of one at a time (tab switches levels)
+    #[arg(long = "side-by-side", default_value_t = false)]
+    pub side_by_side: bool,
+
     /// Specify # of iterations
     #[arg(short = 'i', long = None, default_value = "1000000", value_name="NUM")]
     pub iterations: u32,
 
-    /// Log organism program trace to specified file
+    /// Log organism program trace to specified file (only the organism given with -g, if any)
     #[arg(short = 'l', long = None, value_name="PATH")]
     pub log_path: Option<PathBuf>,
 
+    /// Write the final results as JSON to the specified file, "-" prints them instead of the report
+    #[arg(long = "json", value_name="PATH")]
+    pub json_path: Option<PathBuf>,
+
+    /// Save the complete state of the emulator to the specified file once the run ends or is stopped
+    #[arg(long = "snapshot", value_name="PATH", conflicts_with_all = ["seeds", "tournament_dir"])]
+    pub snapshot_path: Option<PathBuf>,
+
+    /// Carry on from a snapshot instead of starting a new run, the bots have to be given in the same order
+    #[arg(long = "resume", value_name="PATH", value_hint = ValueHint::FilePath, conflicts_with_all = [
+        "scenario_path", "drone_path", "drone_count", "levels", "extended", "compat", "rng", "record_rng", "replay_rng",
+        "seed", "seeds", "tournament_dir",
+    ])]
+    pub resume_path: Option<PathBuf>,
+
+    /// Record every tick of the run to the specified replay file
+    #[arg(long = "save-replay", value_name="PATH", conflicts_with_all = ["seeds", "tournament_dir"])]
+    pub save_replay_path: Option<PathBuf>,
+
+    /// Save how often every tile was visited, eaten from, released on and bumped into at the end of the run,
+    /// a .csv file gets all of them, anything else one PGM image per map next to it
+    #[arg(long = "heatmap", value_name="PATH", conflicts_with_all = ["seeds", "tournament_dir", "replay_path"])]
+    pub heatmap_path: Option<PathBuf>,
+
+    /// Count how often every instruction ran and the energy it took, and write a report of it to the specified file
+    #[arg(long = "profile", value_name="PATH", conflicts_with_all = ["seeds", "tournament_dir", "replay_path"])]
+    pub profile_path: Option<PathBuf>,
+
+    /// Watch a replay file instead of running the emulator
+    #[arg(long = "replay", value_name="PATH", value_hint = ValueHint::FilePath, conflicts_with_all = ["bot_paths", "tournament_dir"])]
+    pub replay_path: Option<PathBuf>,
+
+    /// Follow the original NANORGS executable where it's known to differ, only allowed with the original rules
+    #[arg(long = "compat", default_value_t = false)]
+    pub compat: bool,
+
+    /// Pick the random number generator [default: legacy]
+    #[arg(long = "rng", value_name="RNG", value_enum)]
+    pub rng: Option<RngKind>,
+
+    /// Write every random number drawn, with what it was drawn for, to the specified file
+    #[arg(long = "record-rng", value_name="PATH")]
+    pub record_rng: Option<PathBuf>,
+
+    /// Draw random numbers from a recording instead, and report where the run stops matching it
+    #[arg(long = "replay-rng", value_name="PATH", value_hint = ValueHint::FilePath, conflicts_with = "rng")]
+    pub replay_rng: Option<PathBuf>,
+
     /// Specify the randomization seed
     #[arg(short = 's', long = None)]
     pub seed: Option<u32>,
 
+    /// Run headless once per seed, e.g. "1-100" or "3,7,42", and summarize the results
+    #[arg(long = "seeds", value_name="SEEDS", conflicts_with_all = ["seed", "debug_bot", "log_path", "breakpoints", "watchpoints", "json_path"])]
+    pub seeds: Option<String>,
+
+    /// Specify # of threads used by --seeds and --tournament [default: all cores]
+    #[arg(short = 'j', long = "jobs", value_name="NUM", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: Option<u16>,
+
+    /// Play every .asm file in a directory against each other from both sides over --seeds [default: 1-10] and rank them
+    #[arg(long = "tournament", value_name="DIR", value_hint = ValueHint::DirPath, conflicts_with = "bot_paths")]
+    pub tournament_dir: Option<PathBuf>,
+
+    /// Play every bot of the tournament on its own instead of head to head
+    #[arg(long = "solo", default_value_t = false, requires = "tournament_dir")]
+    pub solo: bool,
+
+    /// Also write the tournament leaderboard to the specified CSV file
+    #[arg(long = "csv", value_name="PATH", requires = "tournament_dir")]
+    pub csv_path: Option<PathBuf>,
+
     /// Show the disassembly and bytecode for this organism
     #[arg(short = 'z', long = None, default_value_t = false)]
     pub show_disassembly: bool,
@@ -0,0 +1,4 @@
+use ruscii::terminal::Color;
+
+/// Bot colors in the tank view, indexed by team
+pub const TEAM_COLORS: [Color; 4] = [Color::White, Color::Xterm(39), Color::Xterm(112), Color::Xterm(220)];
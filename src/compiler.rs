@@ -1,8 +1,11 @@
-use crate::parser::{Instruction, Operand, Parser, ParserToken, PlusMinus, Value};
+use crate::diagnostic::{CompileError, Diagnostic, Span};
+use crate::parser::{Info, Instruction, Operand, Parser, ParserToken, PlusMinus, Value};
 use crate::tokenizer::Tokenizer;
+use byteorder::{BigEndian, ReadBytesExt};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
 use crate::symbol_table::SymbolTable;
 
 pub struct Compiler {
@@ -11,14 +14,18 @@ pub struct Compiler {
     byte_position: usize,
     instruction: ParserToken,
     input: Vec<ParserToken>,
+    spans: Vec<Span>,
     labels: Vec<(String, u16)>,
     label_index: Vec<(String, (u16, u16))>,
-    symbol_table: HashMap<String, u16>,
+    pub symbol_table: HashMap<String, u16>,
+    pub source_lines: HashMap<u16, usize>, // address of every instruction to its line, empty for firmware files
     pub output: Vec<u16>,
+    pub info: Option<Info>, // None for firmware files
+    pub errors: Vec<Diagnostic>,
 }
 
 impl Compiler {
-    pub fn new(input: Vec<ParserToken>, symbol_table: HashMap<String, u16>) -> Compiler {
+    pub fn new(input: Vec<ParserToken>, spans: Vec<Span>, symbol_table: HashMap<String, u16>) -> Compiler {
         let mut compiler = Compiler {
             position: 0,
             read_position: 0,
@@ -27,18 +34,80 @@ impl Compiler {
             output: [0; 3600].to_vec(),
             label_index: Vec::new(),
             input: input.clone(),
+            spans,
             labels: Vec::new(),
             symbol_table,
+            source_lines: HashMap::new(),
+            info: None,
+            errors: Vec::new(),
         };
 
         compiler.read_instruction();
         compiler
     }
 
-    pub fn new_from_file(path: &PathBuf, verbose: bool) -> Compiler {
-        let input: String = fs::read_to_string(path).unwrap().parse().unwrap();
+    // accepts assembly as well as firmware dumped with -f or --dump-bytecode-text
+    pub fn load(path: &PathBuf, verbose: bool, extended: bool) -> Result<Compiler, CompileError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(CompileError::file(path, format!("couldn't read file: {error}"))),
+        };
+
+        match InputFormat::detect(path, &bytes) {
+            InputFormat::Assembly => Compiler::new_from_file(path, verbose, extended),
+            InputFormat::Binary => Compiler::new_from_binary(path, &bytes),
+            InputFormat::HexText => Compiler::new_from_hex_text(path, &bytes),
+        }
+    }
+
+    fn new_from_words(path: &Path, words: Vec<u16>) -> Result<Compiler, CompileError> {
+        if words.len() > 3600 {
+            return Err(CompileError::file(
+                path,
+                format!("firmware is too large: {} words, but bots only have 3600", words.len()),
+            ));
+        }
+
+        let mut compiler = Compiler::new(vec![ParserToken::Eof], vec![], HashMap::new());
+
+        for (pos, word) in words.iter().enumerate() {
+            compiler.output[pos] = *word;
+        }
+
+        Ok(compiler)
+    }
+
+    pub fn new_from_binary(path: &Path, bytes: &[u8]) -> Result<Compiler, CompileError> {
+        if !bytes.len().is_multiple_of(2) {
+            return Err(CompileError::file(path, "firmware has an odd amount of bytes"));
+        }
+
+        let mut reader = Cursor::new(bytes);
+        let mut words = Vec::with_capacity(bytes.len() / 2);
+
+        while let Ok(word) = reader.read_u16::<BigEndian>() {
+            words.push(word);
+        }
+
+        Compiler::new_from_words(path, words)
+    }
+
+    pub fn new_from_hex_text(path: &Path, bytes: &[u8]) -> Result<Compiler, CompileError> {
+        let words = String::from_utf8_lossy(bytes)
+            .split_ascii_whitespace()
+            .map(|word| u16::from_str_radix(word, 16).unwrap())
+            .collect();
+
+        Compiler::new_from_words(path, words)
+    }
 
-        let mut tokenizer = Tokenizer::new(input.clone());
+    pub fn new_from_file(path: &PathBuf, verbose: bool, extended: bool) -> Result<Compiler, CompileError> {
+        let input: String = match fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(error) => return Err(CompileError::file(path, format!("couldn't read file: {error}"))),
+        };
+
+        let mut tokenizer = Tokenizer::new(input.clone(), extended);
 
         let tokens = tokenizer.tokenize();
 
@@ -50,7 +119,7 @@ impl Compiler {
             println!("-------------------------------------------");
         }
 
-        let mut parser = Parser::new(tokens);
+        let mut parser = Parser::new(tokens, tokenizer.spans.clone());
         let mut parser_tokens = Vec::new();
 
         loop {
@@ -58,7 +127,7 @@ impl Compiler {
 
             parser_tokens.push(token.clone());
 
-            if token == ParserToken::EOF {
+            if token == ParserToken::Eof {
                 break;
             }
         }
@@ -69,15 +138,29 @@ impl Compiler {
             }
         }
 
-        let symbol_table = SymbolTable::new(&parser_tokens);
+        let symbol_table = SymbolTable::new(&parser_tokens, &parser.token_spans);
 
         if verbose {
             println!("{:#?}", symbol_table.label_to_address);
         }
 
-        let mut compiler = Compiler::new(parser_tokens, symbol_table.label_to_address);
+        let mut compiler = Compiler::new(parser_tokens, parser.token_spans.clone(), symbol_table.label_to_address);
         compiler.compile();
 
+        let mut diagnostics: Vec<Diagnostic> = tokenizer.errors.clone();
+        diagnostics.extend(parser.errors.clone());
+        diagnostics.extend(symbol_table.errors.clone());
+        diagnostics.extend(compiler.errors.clone());
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.map(|span| span.start));
+
+        if !diagnostics.is_empty() {
+            return Err(CompileError {
+                path: path.clone(),
+                source: input,
+                diagnostics,
+            });
+        }
+
         if verbose {
             println!("{:?}", compiler.output);
 
@@ -87,22 +170,26 @@ impl Compiler {
                 bruh += 1;
                 if bruh == 3 {
                     bruh = 0;
-                    print!("\n");
+                    println!();
                 }
             }
             if bruh != 3 {
-                print!("\n");
+                println!();
             }
         }
 
-        compiler
+        Ok(compiler)
     }
 
     pub fn compile(&mut self) {
         let mut bytecode: Vec<u16> = vec![];
         let mut instruction_pointer = 0;
+        let mut errors: Vec<Diagnostic> = vec![];
+        let mut overflow: Option<Span> = None;
+
+        for (index, token) in self.input.iter().enumerate() {
+            let span = self.spans.get(index).copied().unwrap_or_default();
 
-        for token in &self.input {
             match token {
                 ParserToken::Instruction(instruction) => {
                     while (instruction_pointer % 3) != 0 {
@@ -110,6 +197,8 @@ impl Compiler {
                         instruction_pointer += 1;
                     }
 
+                    self.source_lines.insert(instruction_pointer, span.line);
+
                     let op1 = &instruction.operand1;
                     let op2 = &instruction.operand2;
 
@@ -129,7 +218,7 @@ impl Compiler {
                         Operand::Direct(value) => match value {
                             Value::Number(num) => op1_value = *num,
                             Value::Label(label) => {
-                                op1_value = *self.symbol_table.get(&label.to_lowercase()).unwrap();
+                                op1_value = self.resolve(label, span, &mut errors);
                             }
                         },
                         Operand::ImmediateValue(value) => match value {
@@ -141,7 +230,7 @@ impl Compiler {
                                 }
                             }
                             Value::Label(label) => {
-                                op1_value = *self.symbol_table.get(&label.to_lowercase()).unwrap();
+                                op1_value = self.resolve(label, span, &mut errors);
 
                                 if positional {
                                     op1_value = op1_value.wrapping_sub(instruction_pointer);
@@ -153,13 +242,9 @@ impl Compiler {
                         }
                         Operand::RegisterIndexedDirect(base, operator, offset) => {
                             match base.as_ref() {
-                                Operand::ImmediateValue(value) => match value {
-                                    Value::Label(label) => {
-                                        op1_offset =
-                                            *self.symbol_table.get(&label.to_lowercase()).unwrap();
-                                    }
-                                    _ => {}
-                                },
+                                Operand::ImmediateValue(Value::Label(label)) => {
+                                    op1_offset = self.resolve(label, span, &mut errors);
+                                }
                                 Operand::Register(register) => {
                                     op1_value = (register.to_owned() as u16) << 12;
                                 }
@@ -169,32 +254,26 @@ impl Compiler {
                                 Operand::ImmediateValue(value) => {
                                     op1_offset = match value {
                                         Value::Number(num) => *num,
-                                        Value::Label(_) =>
-                                            panic!("Label cannot be used as offset")
+                                        Value::Label(label) => {
+                                            errors.push(Compiler::label_offset_error(label, span));
+                                            0
+                                        }
                                     };
                                 }
                                 Operand::Register(register) => {
                                     match base.as_ref() {
-                                        Operand::Register(_) => panic!("Register cannot be used as offset"), // invalid
-                                        Operand::ImmediateValue(value) => match value {
-                                            Value::Label(_) => {
-                                                op1_value = (register.to_owned() as u16) << 12
-                                            }
-                                            _ => {}
-                                        },
+                                        Operand::Register(_) => errors.push(Compiler::register_offset_error(span)), // invalid
+                                        Operand::ImmediateValue(Value::Label(_)) => {
+                                            op1_value = (register.to_owned() as u16) << 12
+                                        }
                                         _ => {}
                                     }
                                 }
                                 _ => {}
                             }
-                            match operator {
-                                PlusMinus::Minus => {
-                                    if op1_offset > 0 {
-                                        op1_offset = 0u16.wrapping_sub(op1_offset);
-                                        op1_carry = true;
-                                    }
-                                }
-                                _ => {}
+                            if *operator == PlusMinus::Minus && op1_offset > 0 {
+                                op1_offset = 0u16.wrapping_sub(op1_offset);
+                                op1_carry = true;
                             }
                         }
                     }
@@ -204,7 +283,7 @@ impl Compiler {
                         Operand::Direct(value) => match value {
                             Value::Number(num) => op2_value = *num,
                             Value::Label(label) => {
-                                op2_value = *self.symbol_table.get(&label.to_lowercase()).unwrap();
+                                op2_value = self.resolve(label, span, &mut errors);
                             }
                         },
                         Operand::ImmediateValue(value) => match value {
@@ -216,7 +295,7 @@ impl Compiler {
                                 }
                             }
                             Value::Label(label) => {
-                                op2_value = *self.symbol_table.get(&label.to_lowercase()).unwrap();
+                                op2_value = self.resolve(label, span, &mut errors);
                                 if positional {
                                     op2_value = op2_value.wrapping_sub(instruction_pointer);
                                 }
@@ -227,13 +306,9 @@ impl Compiler {
                         }
                         Operand::RegisterIndexedDirect(base, operator, offset) => {
                             match base.as_ref() {
-                                Operand::ImmediateValue(value) => match value {
-                                    Value::Label(label) => {
-                                        op2_offset =
-                                            *self.symbol_table.get(&label.to_lowercase()).unwrap();
-                                    }
-                                    _ => {}
-                                },
+                                Operand::ImmediateValue(Value::Label(label)) => {
+                                    op2_offset = self.resolve(label, span, &mut errors);
+                                }
                                 Operand::Register(register) => {
                                     op2_value = (register.to_owned() as u16) << 12;
                                 }
@@ -243,31 +318,26 @@ impl Compiler {
                                 Operand::ImmediateValue(value) => {
                                     op2_offset = match value {
                                         Value::Number(num) => *num,
-                                        Value::Label(_) => panic!("Label cannot be used as offset"),
+                                        Value::Label(label) => {
+                                            errors.push(Compiler::label_offset_error(label, span));
+                                            0
+                                        }
                                     };
                                 }
                                 Operand::Register(register) => {
                                     match base.as_ref() {
-                                        Operand::Register(_) => panic!("Register cannot be used as offset"),
-                                        Operand::ImmediateValue(value) => match value {
-                                            Value::Label(_) => {
-                                                op2_value = (register.to_owned() as u16) << 12
-                                            }
-                                            _ => {}
-                                        },
+                                        Operand::Register(_) => errors.push(Compiler::register_offset_error(span)),
+                                        Operand::ImmediateValue(Value::Label(_)) => {
+                                            op2_value = (register.to_owned() as u16) << 12
+                                        }
                                         _ => {}
                                     }
                                 }
                                 _ => {}
                             }
-                            match operator {
-                                PlusMinus::Minus => {
-                                    if op2_offset > 0 {
-                                        op2_offset = 0u16.wrapping_sub(op2_offset);
-                                        op2_carry = true;
-                                    }
-                                }
-                                _ => {}
+                            if *operator == PlusMinus::Minus && op2_offset > 0 {
+                                op2_offset = 0u16.wrapping_sub(op2_offset);
+                                op2_carry = true;
                             }
                         }
                     }
@@ -287,19 +357,67 @@ impl Compiler {
                         bytecode.push(match value {
                             Value::Number(num) => *num,
                             Value::Label(label) => {
-                                *self.symbol_table.get(&label.to_lowercase()).unwrap()
+                                self.resolve(label, span, &mut errors)
                             }
                         });
                         instruction_pointer += 1;
                     }
                 }
+                ParserToken::BotInfo(info) => match Info::parse(info) {
+                    _ if self.info.is_some() => errors.push(
+                        Diagnostic::new("duplicate info line", span).with_label("the bot was already described above"),
+                    ),
+                    Ok(info) => self.info = Some(info),
+                    Err(label) => errors.push(Diagnostic::new("malformed info line", span).with_label(label)),
+                },
                 _ => {}
             }
+
+            if bytecode.len() > self.output.len() && overflow.is_none() {
+                overflow = Some(span);
+            }
+        }
+
+        if let Some(span) = overflow {
+            errors.push(
+                Diagnostic::new(
+                    format!("program is too large: {} words, but bots only have 3600", bytecode.len()),
+                    span,
+                )
+                .with_label("memory runs out here"),
+            );
         }
 
-        for (pos, word) in bytecode.iter().enumerate() {
+        for (pos, word) in bytecode.iter().take(self.output.len()).enumerate() {
             self.output[pos] = *word;
         }
+
+        self.errors = errors;
+    }
+
+    fn resolve(&self, label: &str, span: Span, errors: &mut Vec<Diagnostic>) -> u16 {
+        match self.symbol_table.get(&label.to_lowercase()) {
+            Some(address) => *address,
+            None => {
+                errors.push(
+                    Diagnostic::new(format!("unknown label `{label}`"), span)
+                        .with_label("not defined anywhere in this file")
+                        .with_focus(label),
+                );
+                0
+            }
+        }
+    }
+
+    fn label_offset_error(label: &str, span: Span) -> Diagnostic {
+        Diagnostic::new("label cannot be used as an offset", span)
+            .with_label("only numbers or registers can be added to a label")
+            .with_focus(label)
+    }
+
+    fn register_offset_error(span: Span) -> Diagnostic {
+        Diagnostic::new("register cannot be used as an offset", span)
+            .with_label("only numbers can be added to a register")
     }
 
     fn get_modes(instruction: &Instruction, op1_carry: bool, op2_carry: bool) -> u16 {
@@ -319,7 +437,7 @@ impl Compiler {
     }
 
     fn read_instruction(&mut self) {
-        if !(self.instruction == ParserToken::EOF) {
+        if !(self.instruction == ParserToken::Eof) {
             self.instruction = self.input[self.read_position].clone();
         }
 
@@ -327,3 +445,149 @@ impl Compiler {
         self.read_position += 1;
     }
 }
+
+#[derive(Debug, PartialEq)]
+pub enum InputFormat {
+    Assembly,
+    Binary,  // big-endian words, as written by -f
+    HexText, // hex words, three per line, as written by --dump-bytecode-text
+}
+
+impl InputFormat {
+    pub fn detect(path: &Path, bytes: &[u8]) -> InputFormat {
+        let is_text = bytes
+            .iter()
+            .all(|byte| !byte.is_ascii_control() || byte.is_ascii_whitespace());
+
+        if !is_text || path.extension().is_some_and(|ext| ext == "bin") {
+            return InputFormat::Binary;
+        }
+
+        let mut words = String::from_utf8_lossy(bytes)
+            .split_ascii_whitespace()
+            .map(|word| word.len() == 4 && word.chars().all(|c| c.is_ascii_hexdigit()))
+            .collect::<Vec<bool>>()
+            .into_iter();
+
+        if words.len() > 0 && words.all(|is_word| is_word) {
+            InputFormat::HexText
+        } else {
+            InputFormat::Assembly
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_the_input_format() {
+        let path = PathBuf::from("bot.asm");
+
+        assert_eq!(InputFormat::detect(&path, b"main:\n    mov r0, 1\n"), InputFormat::Assembly);
+        assert_eq!(InputFormat::detect(&path, b""), InputFormat::Assembly);
+        assert_eq!(InputFormat::detect(&path, b"0001 0002 abcd\nFFFF 0000 0000\n"), InputFormat::HexText);
+        assert_eq!(InputFormat::detect(&path, &[0x80, 0x06, 0x00, 0x15]), InputFormat::Binary);
+        // .bin is what -f writes, even if the content happens to look like text
+        assert_eq!(InputFormat::detect(&PathBuf::from("bot.bin"), b"0001 0002 0003"), InputFormat::Binary);
+    }
+
+    #[test]
+    fn loads_binary_firmware() {
+        let path = PathBuf::from("bot.bin");
+        let compiler = Compiler::new_from_binary(&path, &[0x80, 0x06, 0x00, 0x15, 0xAB, 0xCD]).unwrap();
+
+        assert_eq!(compiler.output[..4], [0x8006, 0x0015, 0xABCD, 0]);
+        assert_eq!(compiler.output.len(), 3600);
+        assert!(compiler.info.is_none());
+    }
+
+    #[test]
+    fn loads_hex_text_firmware() {
+        let path = PathBuf::from("bot.txt");
+        let compiler = Compiler::new_from_hex_text(&path, b"8006 0015 0000\nabcd ffff 0001\n").unwrap();
+
+        assert_eq!(compiler.output[..7], [0x8006, 0x0015, 0, 0xABCD, 0xFFFF, 1, 0]);
+    }
+
+    #[test]
+    fn rejects_broken_firmware() {
+        let path = PathBuf::from("bot.bin");
+
+        assert!(Compiler::new_from_binary(&path, &[0x80, 0x06, 0x00]).is_err());
+        assert!(Compiler::new_from_binary(&path, &vec![0; 3601 * 2]).is_err());
+        assert!(Compiler::new_from_binary(&path, &vec![0; 3600 * 2]).is_ok());
+    }
+
+    #[test]
+    fn dumped_firmware_matches_its_source() {
+        let source = Compiler::load(&PathBuf::from("bots/samplebot.asm"), false, false).unwrap();
+        let firmware = Compiler::load(&PathBuf::from("bots/samplebot.asm.bin"), false, false).unwrap();
+
+        assert_eq!(source.output, firmware.output);
+    }
+
+    // assembles `source` from a file in the temp directory, named after the test
+    fn assemble(name: &str, source: &str) -> Result<Compiler, CompileError> {
+        let path = std::env::temp_dir().join(format!("open_nanorgs_{name}.asm"));
+        fs::write(&path, source).unwrap();
+        Compiler::new_from_file(&path, false, false)
+    }
+
+    #[test]
+    fn reports_every_error_with_its_source() {
+        let source = "main:\n    mov r0, missing\n    jmp main\nmain:\n    add r0, [r1+r2]\n    mov r0, 99999\n";
+        let error = assemble("errors", source).err().unwrap();
+
+        let messages: Vec<&str> = error.diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown label `missing`",
+                "label `main` is already defined",
+                "register cannot be used as an offset",
+                "invalid number `99999`",
+            ]
+        );
+
+        let rendered = error.to_string();
+        let path = error.path.display();
+        assert!(rendered.starts_with(&format!(
+            "error: unknown label `missing`\n \
+             --> {path}:2:13\n  \
+             |\n\
+             2 |     mov r0, missing\n  \
+             |             ^^^^^^^ not defined anywhere in this file\n"
+        )));
+        assert!(rendered.contains(&format!(
+            "error: label `main` is already defined\n \
+             --> {path}:4:1\n  \
+             |\n\
+             4 | main:\n  \
+             | ^^^^ first defined on line 1\n"
+        )));
+        assert!(rendered.ends_with("error: could not assemble `open_nanorgs_errors.asm` due to 4 previous errors"));
+    }
+
+    #[test]
+    fn reports_bad_operands() {
+        let source = "    mov r0, [missing+r1]\n    push [r1-]\n    mov [r1+label], 1\nlabel:\n    add r0\n";
+        let error = assemble("operands", source).err().unwrap();
+
+        let found: Vec<(&str, usize)> = error
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span.unwrap().line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("unknown label `missing`", 1),
+                ("expected offset, found `]`", 2),
+                ("label cannot be used as an offset", 3),
+                ("expected `,` between operands, found end of file", 6),
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::breakpoints::Breakpoints;
+use crate::disassembler::Disassembler;
+use crate::emulator::{Bot, Emulator};
+use ruscii::drawing::Pencil;
+use ruscii::keyboard::Key;
+use ruscii::spatial::Vec2;
+use ruscii::terminal::Color;
+
+/// Upper bound of ticks ran per frame while stepping, keeps the UI responsive on long runs
+const MAX_TICKS_PER_FRAME: u32 = 1000;
+
+/// Amount of instructions shown before and after the current one
+const DISASSEMBLY_CONTEXT: u16 = 6;
+
+pub struct Debugger {
+    pub bot_id: u16,
+    paused: bool,
+    run_to: Option<u32>,
+    input: String,
+    message: Option<String>,
+}
+
+impl Debugger {
+    pub fn new(bot_id: u16) -> Debugger {
+        Debugger {
+            bot_id,
+            paused: true,
+            run_to: None,
+            input: String::new(),
+            message: None,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused && self.run_to.is_none()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.run_to = None;
+    }
+
+    /// Pauses execution and focuses the bot that hit a breakpoint
+    pub fn break_at(&mut self, bot_id: u16, message: String) {
+        self.pause();
+        self.bot_id = bot_id;
+        self.message = Some(message);
+    }
+
+    pub fn handle_key(&mut self, key: Key, current_tick: u32) {
+        self.message = None;
+
+        match key {
+            Key::Space => {
+                self.paused = !self.paused;
+                self.run_to = None;
+            }
+            Key::S | Key::Enter => {
+                self.paused = true;
+                self.run_to = Some(current_tick + 1);
+            }
+            Key::N => {
+                let count = self.take_input().unwrap_or(1);
+                self.paused = true;
+                self.run_to = Some(current_tick.saturating_add(count));
+            }
+            Key::T => {
+                if let Some(tick) = self.take_input() {
+                    self.paused = true;
+                    self.run_to = Some(tick);
+                }
+            }
+            Key::Backspace => {
+                self.input.pop();
+            }
+            _ => {
+                if let Some(digit) = Self::digit(key) {
+                    if self.input.len() < 10 {
+                        self.input.push(digit);
+                    }
+                }
+            }
+        }
+    }
+
+    fn digit(key: Key) -> Option<char> {
+        match key {
+            Key::Num0 => Some('0'),
+            Key::Num1 => Some('1'),
+            Key::Num2 => Some('2'),
+            Key::Num3 => Some('3'),
+            Key::Num4 => Some('4'),
+            Key::Num5 => Some('5'),
+            Key::Num6 => Some('6'),
+            Key::Num7 => Some('7'),
+            Key::Num8 => Some('8'),
+            Key::Num9 => Some('9'),
+            _ => None,
+        }
+    }
+
+    fn take_input(&mut self) -> Option<u32> {
+        let value = self.input.parse().ok();
+        self.input.clear();
+        value
+    }
+
+    /// Returns how many ticks should be emulated during this frame
+    pub fn ticks_to_run(&mut self, current_tick: u32) -> u32 {
+        match self.run_to {
+            Some(target) => {
+                if current_tick >= target {
+                    self.run_to = None;
+                    0
+                } else {
+                    (target - current_tick).min(MAX_TICKS_PER_FRAME)
+                }
+            }
+            None => {
+                if self.paused {
+                    0
+                } else {
+                    1
+                }
+            }
+        }
+    }
+
+    pub fn state_lines(bot: &Bot) -> Vec<String> {
+        let mut lines = vec![
+            format!("IP: {:04}  SP: {:04}  Flags: {}", bot.instruction_pointer, bot.stack_pointer, bot.flags),
+            format!(
+                "Energy: {}  Pos: {},{},{}{}",
+                bot.energy,
+                bot.position.x,
+                bot.position.y,
+                bot.position.z,
+                if bot.sleeping { "  (sleeping)" } else { "" }
+            ),
+        ];
+
+        for row in 0..7 {
+            let left = row * 2;
+            let right = left + 1;
+            lines.push(format!(
+                "r{:<2}: {:04X} ({:>5})  r{:<2}: {:04X} ({:>5})",
+                left, bot.registers[left], bot.registers[left], right, bot.registers[right], bot.registers[right]
+            ));
+        }
+
+        lines
+    }
+
+    pub fn draw(&self, pencil: &mut Pencil, emulator: &Emulator, breakpoints: &Breakpoints, origin: Vec2) {
+        let bot = match emulator.bots.iter().find(|bot| bot.id == self.bot_id) {
+            Some(bot) => bot,
+            None => return,
+        };
+
+        let mut line = 0;
+        let mut draw_line = |pencil: &mut Pencil, text: &str| {
+            pencil.draw_text(text, origin + Vec2::y(line));
+            line += 1;
+        };
+
+        pencil.set_foreground(Color::Xterm(172));
+        let status = match (self.is_paused(), self.run_to) {
+            (true, _) => "PAUSED".to_string(),
+            (false, Some(target)) => format!("RUNNING TO {target}"),
+            (false, None) => "RUNNING".to_string(),
+        };
+        draw_line(pencil, &format!("Debugging {} [{}]", bot.get_name(), status));
+
+        pencil.set_foreground(Color::White);
+        draw_line(pencil, &format!("Tick: {}/{}", emulator.current_tick, emulator.iterations));
+
+        for state_line in Self::state_lines(bot) {
+            draw_line(pencil, &state_line);
+        }
+
+        draw_line(pencil, "");
+
+        let disassembler = Disassembler::new(bot.program_memory.to_vec(), emulator.scenario.extended);
+        let ip = bot.instruction_pointer;
+        let first = ip.saturating_sub(DISASSEMBLY_CONTEXT * 3);
+        let last = (ip + DISASSEMBLY_CONTEXT * 3).min(bot.program_memory.len() as u16 - 3);
+
+        for address in (first..=last).step_by(3) {
+            let marker = match (address == ip, breakpoints.has_breakpoint(bot, address)) {
+                (true, true) => "*>",
+                (true, false) => " >",
+                (false, true) => "* ",
+                (false, false) => "  ",
+            };
+
+            if address == ip {
+                pencil.set_foreground(Color::Xterm(172));
+            }
+            draw_line(pencil, &format!("{} {:04}  {}", marker, address, disassembler.parse_at(address, true)));
+            pencil.set_foreground(Color::White);
+        }
+
+        draw_line(pencil, "");
+
+        if let Some(message) = &self.message {
+            pencil.set_foreground(Color::Xterm(172));
+            draw_line(pencil, message);
+            pencil.set_foreground(Color::White);
+        }
+
+        draw_line(pencil, &format!("Count: {}_", self.input));
+        pencil.set_foreground(Color::Grey);
+        draw_line(pencil, "space: pause/resume  s: step");
+        draw_line(pencil, "<num> n: step num ticks");
+        draw_line(pencil, "<num> t: run to tick num");
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Location of a piece of source code, `start` and `end` are byte offsets
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize, // 1-based
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize) -> Span {
+        Span { start, end, line }
+    }
+
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            line: self.line.min(other.line),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+    pub label: Option<String>,
+    // narrows the underline to this text, if it's found inside the span
+    pub focus: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Span) -> Diagnostic {
+        Diagnostic {
+            message: message.into(),
+            span: Some(span),
+            label: None,
+            focus: None,
+        }
+    }
+
+    // for errors that concern the whole file
+    pub fn file(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            message: message.into(),
+            span: None,
+            label: None,
+            focus: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Diagnostic {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_focus(mut self, focus: impl Into<String>) -> Diagnostic {
+        self.focus = Some(focus.into());
+        self
+    }
+
+    /// Renders the diagnostic in the same fashion as rustc, including a snippet of the source
+    pub fn render(&self, path: &Path, source: &str) -> String {
+        let mut output = format!("error: {}\n", self.message);
+
+        let span = match self.span {
+            Some(span) if span.start <= source.len() => span,
+            _ => {
+                output += &format!(" --> {}\n", path.display());
+                return output;
+            }
+        };
+
+        let (start, end) = self.underline(&span, source);
+
+        let line_start = source[..start].rfind('\n').map_or(0, |pos| pos + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |pos| start + pos);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        let line_number = source[..start].matches('\n').count() + 1;
+
+        let prefix = expand_tabs(&source[line_start..start]);
+        let column = prefix.chars().count() + 1;
+        let underline_length = expand_tabs(&source[start..end.min(line_end).max(start)]).chars().count().max(1);
+
+        let gutter = " ".repeat(line_number.to_string().len());
+
+        output += &format!("{gutter}--> {}:{}:{}\n", path.display(), line_number, column);
+        output += &format!("{gutter} |\n");
+        output += &format!("{line_number} | {}\n", expand_tabs(line));
+        output += &format!(
+            "{gutter} | {}{}",
+            " ".repeat(prefix.chars().count()),
+            "^".repeat(underline_length)
+        );
+
+        if let Some(label) = &self.label {
+            output += &format!(" {label}");
+        }
+
+        output += "\n";
+        output
+    }
+
+    fn underline(&self, span: &Span, source: &str) -> (usize, usize) {
+        let end = span.end.min(source.len());
+
+        if let Some(focus) = &self.focus {
+            let text = source[span.start..end].to_lowercase();
+            if let Some(offset) = text.find(&focus.to_lowercase()) {
+                let start = span.start + offset;
+                return (start, start + focus.len());
+            }
+        }
+
+        (span.start, end)
+    }
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', "    ")
+}
+
+/// Every error found while assembling a file
+#[derive(Debug)]
+pub struct CompileError {
+    pub path: PathBuf,
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CompileError {
+    pub fn file(path: &Path, message: impl Into<String>) -> CompileError {
+        CompileError {
+            path: path.to_path_buf(),
+            source: String::new(),
+            diagnostics: vec![Diagnostic::file(message)],
+        }
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic.render(&self.path, &self.source))?;
+        }
+
+        let file_name = self
+            .path
+            .file_name()
+            .map_or(self.path.display().to_string(), |name| name.to_string_lossy().to_string());
+
+        match self.diagnostics.len() {
+            1 => write!(f, "error: could not assemble `{}` due to 1 previous error", file_name),
+            count => write!(f, "error: could not assemble `{}` due to {} previous errors", file_name, count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_a_snippet_with_the_focus_underlined() {
+        let source = "main:\n\tjmp loop\n";
+        let diagnostic = Diagnostic::new("unknown label `loop`", Span::new(6, 15, 2))
+            .with_label("not defined anywhere in this file")
+            .with_focus("loop");
+
+        assert_eq!(
+            diagnostic.render(&PathBuf::from("bot.asm"), source),
+            "error: unknown label `loop`\n \
+             --> bot.asm:2:9\n  \
+             |\n\
+             2 |     jmp loop\n  \
+             |         ^^^^ not defined anywhere in this file\n"
+        );
+    }
+
+    #[test]
+    fn renders_file_errors_without_a_snippet() {
+        let diagnostic = Diagnostic::file("couldn't read file");
+
+        assert_eq!(
+            diagnostic.render(&PathBuf::from("bot.asm"), ""),
+            "error: couldn't read file\n --> bot.asm\n"
+        );
+    }
+}
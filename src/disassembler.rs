@@ -1,20 +1,24 @@
+use crate::parser::Info;
 use crate::tokenizer::InstructionType;
 
 pub struct Disassembler {
     bytecode: Vec<u16>,
+    extended: bool,
 }
 
 impl Disassembler {
-    pub fn new(bytecode: Vec<u16>) -> Disassembler {
-        let disassembler = Disassembler {
-            bytecode
-        };
-
-        disassembler
+    pub fn new(bytecode: Vec<u16>, extended: bool) -> Disassembler {
+        Disassembler {
+            bytecode,
+            extended,
+        }
     }
 
-    pub fn print_disassembly(&self, bot_name: String) {
-        print!("Disassembly of {bot_name}:\n\n");
+    pub fn print_disassembly(&self, bot_name: String, info: Option<&Info>) {
+        match info {
+            Some(info) => print!("Disassembly of {bot_name} ({info}):\n\n"),
+            None => print!("Disassembly of {bot_name}:\n\n"),
+        }
         let instruction_count: u16 = (self.bytecode.len() / 3) as u16;
 
         for i in 0..instruction_count {
@@ -26,7 +30,7 @@ impl Disassembler {
                 .map(|&num| format!("{:04X}", num))
                 .collect::<Vec<_>>().join(" ");
 
-            println!("{:04}  {:<30} ({})", ip, Self::parse(instruction, ip, true), byte_string);
+            println!("{:04}  {:<30} ({})", ip, Self::parse(instruction, ip, true, self.extended), byte_string);
         }
     }
 
@@ -37,13 +41,13 @@ impl Disassembler {
     }
 
     pub fn parse_at(&self, ip: u16, data_allowed: bool) -> String {
-        Self::parse(self.get_instruction(ip), ip, data_allowed)
+        Self::parse(self.get_instruction(ip), ip, data_allowed, self.extended)
     }
 
-    pub fn parse(bytes: [u16; 3], instruction_pointer: u16, data_allowed: bool) -> String {
+    pub fn parse(bytes: [u16; 3], instruction_pointer: u16, data_allowed: bool, extended: bool) -> String {
         let mut result = String::new();
 
-        let is_data = bytes[0] & 0xFF > InstructionType::CKSUM as u16;
+        let is_data = bytes[0] & 0xFF > InstructionType::last_opcode(extended);
 
         if is_data && data_allowed {
             return format!("data {{ {} {} {} }}", bytes[0], bytes[1], bytes[2]);
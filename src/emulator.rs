@@ -1,19 +1,39 @@
+use crate::heatmap::{HeatmapKind, Heatmaps};
 use crate::parser::{Operand, PlusMinus, Register, Value};
-use crate::rng::{LegacyRNG, ModernRNG, RNGSystem};
+use crate::profiler::Profiler;
+use crate::replay::Recorder;
+use crate::rng::RNGSystem;
+use crate::scenario::Scenario;
 use crate::tokenizer::InstructionType;
+use crate::trace::{BotState, Tracer};
 use ruscii::spatial::Vec2;
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 use std::cmp::PartialEq;
 use std::collections::HashSet;
 use std::fmt::Formatter;
+use std::ops::{BitAnd, BitOr, BitXor};
 
-#[derive(Debug)]
+/// Amount of collection points placed in the tank, approximate, the original's count isn't verified
+pub const COLLECTION_POINTS: usize = 5;
+
+/// Value reported by SENSE when standing on a collection point
+pub const COLLECTION_POINT_ID: u16 = 0xFFFF;
+
+/// Value reported by SENSE when standing on a ramp
+pub const RAMP_ID: u16 = 0xFFFE;
+
+/// Amount of ramps linking each level to the one below it
+pub const RAMPS_PER_LEVEL: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ItemType {
     Sludge,
     CollectionPoint,
     Ramp,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
     pub id: u16,
     pub position: Position,
@@ -30,22 +50,29 @@ impl Item {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tank {
     bounds: Position,
     pub score: u64,
+    pub team_scores: Vec<u64>,
+    pub sludge_eaten: u32,
     pub sludge_types: u8,
     pub toxic_sludge: Vec<u8>,
     pub elements: Vec<Option<Item>>,
+    #[serde(skip)]
+    pub heatmaps: Option<Heatmaps>, // only gathered when something shows or saves them
 }
 
 impl Tank {
-    pub fn new(bounds: Position) -> Tank {
+    pub fn new(bounds: Position, teams: usize) -> Tank {
         let mut tank = Tank {
             score: 0,
+            team_scores: vec![0; teams],
+            sludge_eaten: 0,
             sludge_types: 0,
             toxic_sludge: vec![],
             elements: vec![],
+            heatmaps: None,
             bounds,
         };
         tank.elements.resize_with(
@@ -55,6 +82,10 @@ impl Tank {
         tank
     }
 
+    pub fn bounds(&self) -> Position {
+        self.bounds
+    }
+
     fn get_index(&self, pos: &Position) -> usize {
         //println!("checking index of {:?}", pos);
         usize::from(pos.x)
@@ -76,14 +107,25 @@ impl Tank {
         });
     }
 
+    pub fn remove_item(&mut self, pos: &Position) {
+        let index = self.get_index(pos);
+        self.elements[index] = None;
+    }
+
+    // returns whether the eaten sludge was toxic
     fn eat_item(&mut self, pos: &Position) -> bool {
         let index = self.get_index(pos);
         match &self.elements[index] {
             Some(item) => {
                 match item.item_type {
                     ItemType::Sludge => {
+                        let toxic = self.is_toxic(item.id);
                         self.elements[index] = None;
-                        return false; // TODO: return toxicity
+                        self.sludge_eaten += 1;
+                        if let Some(heatmaps) = &mut self.heatmaps {
+                            heatmaps.add(HeatmapKind::Eaten, pos, 1);
+                        }
+                        toxic
                     }
                     _ => panic!("this shouldn't happen"),
                 }
@@ -92,12 +134,16 @@ impl Tank {
         }
     }
 
+    pub fn is_toxic(&self, sludge_type: u16) -> bool {
+        self.toxic_sludge.iter().any(|&toxic| toxic as u16 == sludge_type)
+    }
+
     pub fn get_random_position(&self, rng: &mut Box<dyn RNGSystem>) -> Position {
         loop {
             let pos = Position {
-                x: rng.rand(Some((self.bounds.x - 1) as u32)) as u8,
-                y: rng.rand(Some((self.bounds.y - 1) as u32)) as u8,
-                z: rng.rand(Some((self.bounds.z - 1) as u32)) as u8,
+                x: rng.rand("item_x", Some((self.bounds.x - 1) as u32)) as u8,
+                y: rng.rand("item_y", Some((self.bounds.y - 1) as u32)) as u8,
+                z: self.random_level("item_z", rng),
             };
 
             if !self.has_item(&pos) {
@@ -106,6 +152,14 @@ impl Tank {
         }
     }
 
+    // the original only has a single level and never draws one, neither do single level tanks here
+    pub fn random_level(&self, caller: &'static str, rng: &mut Box<dyn RNGSystem>) -> u8 {
+        match self.bounds.z {
+            1 => 0,
+            levels => rng.rand(caller, Some((levels - 1) as u32)) as u8,
+        }
+    }
+
     // TODO: rework this method to work the same way as NANORGS
     fn calculate_toxic(amount: u8, rng: &mut Box<dyn RNGSystem>) -> Vec<u8> {
         let toxic_count = (amount / 5) as usize;
@@ -113,7 +167,7 @@ impl Tank {
         let mut toxic = HashSet::new();
 
         while toxic.len() < toxic_count {
-            let num = rng.rand(Some((amount + 1) as u32)) as u8;
+            let num = rng.rand("toxic_sludge", Some((amount + 1) as u32)) as u8;
             toxic.insert(num);
         }
 
@@ -122,27 +176,75 @@ impl Tank {
 
     // TODO: rework this method to work the same way as NANORGS
     pub fn initial_fill(&mut self, num_items: usize, rng: &mut Box<dyn RNGSystem>) {
-        self.sludge_types = rng.rand(32.into()) as u8;
+        self.sludge_types = rng.rand("sludge_types", 32.into()) as u8;
         self.toxic_sludge = Tank::calculate_toxic(self.sludge_types, rng);
 
+        // collection points are placed first, so sludge never spawns on top of them
+        // approximate, the order the original places them in isn't verified
+        for _ in 0..COLLECTION_POINTS {
+            let pos = self.get_random_position(rng);
+            self.add_item(ItemType::CollectionPoint, COLLECTION_POINT_ID, pos);
+        }
+
+        self.place_ramps(rng);
+
         for _ in 0..num_items {
             let pos = self.get_random_position(rng);
-            let rand_id = rng.rand(Some(self.sludge_types as u32)) as u16;
+            let rand_id = rng.rand("sludge_type", Some(self.sludge_types as u32)) as u16;
             self.add_item(ItemType::Sludge, rand_id, pos);
         }
     }
 
-    fn get_item(&self, pos: &Position) -> Option<&Item> {
+    // a ramp has an end on both levels it links, each one leads to the other
+    fn place_ramps(&mut self, rng: &mut Box<dyn RNGSystem>) {
+        for level in 1..self.bounds.z {
+            for _ in 0..RAMPS_PER_LEVEL {
+                let (lower, upper) = loop {
+                    let x = rng.rand("ramp_x", Some((self.bounds.x - 1) as u32)) as u8;
+                    let y = rng.rand("ramp_y", Some((self.bounds.y - 1) as u32)) as u8;
+
+                    let lower = Position::new(x, y, level - 1);
+                    let upper = Position::new(x, y, level);
+
+                    if !self.has_item(&lower) && !self.has_item(&upper) {
+                        break (lower, upper);
+                    }
+                };
+
+                self.add_item(ItemType::Ramp, level as u16, lower);
+                self.add_item(ItemType::Ramp, level as u16 - 1, upper);
+            }
+        }
+    }
+
+    pub fn get_item(&self, pos: &Position) -> Option<&Item> {
         let index = self.get_index(pos);
         self.elements[index].as_ref()
     }
 
-    pub fn deposit(&mut self, amount: u16, pos: &Position) -> bool {
+    // the id of a ramp is the level found at its other end
+    pub fn ramp_destination(&self, pos: &Position) -> Option<Position> {
+        match self.get_item(pos) {
+            Some(item) if matches!(item.item_type, ItemType::Ramp) && item.id < self.bounds.z as u16 => {
+                Some(Position::new(pos.x, pos.y, item.id as u8))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn deposit(&mut self, amount: u16, pos: &Position, team: Option<usize>) -> bool {
+        if let Some(heatmaps) = &mut self.heatmaps {
+            heatmaps.add(HeatmapKind::Released, pos, amount as u64);
+        }
+
         match self.get_item(pos) {
             Some(item) => match item.item_type {
-                ItemType::Sludge => {
+                ItemType::CollectionPoint => {
                     self.score += amount as u64;
-                    return true;
+                    if let Some(team) = team {
+                        self.team_scores[team] += amount as u64;
+                    }
+                    true
                 }
                 _ => false,
             },
@@ -189,21 +291,24 @@ impl Tank {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bot {
     pub id: u16,
+    pub team: Option<usize>, // None for drones
     pub position: Position,
     pub energy: u16,
     pub sleeping: bool,
+    pub mutations: u32,
 
     // CPU related
     pub instruction_pointer: u16,
     pub stack_pointer: u16,
     pub registers: [u16; 14],
+    #[serde(with = "BigArray")]
     pub program_memory: [u16; 3600],
     pub flags: CPUFlags,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CPUFlags {
     pub success: bool,
     pub less: bool,
@@ -249,15 +354,30 @@ impl std::fmt::Display for CPUFlags {
     }
 }
 
+/// Stock firmware of the malicious drones
+pub const DRONE_FIRMWARE: [u16; 102] = [
+    0x8004, 0x000F, 0x0000, 0x8004, 0x0018, 0x0000, 0x8004, 0x002A, 0x0000, 0x8004, 0x0045,
+    0x0000, 0x8006, 0xFFF7, 0x0000, 0x2020, 0x0DFB, 0x0004, 0x2020, 0x0DFC, 0x000A, 0x200F,
+    0x0DFC, 0x0001, 0x0005, 0x0000, 0x0000, 0x401E, 0x0002, 0x0000, 0x800E, 0x000F, 0x0000,
+    0x401A, 0x0002, 0x0000, 0x6017, 0x0002, 0x2710, 0x8009, 0x0006, 0x0000, 0x001F, 0x0000,
+    0x0000, 0x0005, 0x0000, 0x0000, 0x4001, 0x0000, 0x0DFB, 0x6020, 0x0001, 0x0DF8, 0x5024,
+    0x0000, 0x0001, 0x800E, 0x0012, 0x0000, 0x7017, 0x0000, 0x1000, 0x800B, 0x000C, 0x0000,
+    0x6020, 0x0001, 0x0E10, 0x7001, 0x0000, 0x1000, 0x1023, 0x0DFB, 0x0001, 0x0005, 0x0000,
+    0x0000, 0x2017, 0x0DFC, 0x0000, 0x800B, 0x000F, 0x0000, 0x001B, 0x0DFB, 0x0000, 0x800E,
+    0x0009, 0x0000, 0x2010, 0x0DFC, 0x0001, 0x0005, 0x0000, 0x0000, 0x8004, 0xFFAF, 0x0000,
+    0x8006, 0xFFEB, 0x0000,
+];
+
 macro_rules! simple_math_instr {
-    ($idx:expr, $dest:expr, $src:expr, $bots:expr, $op:tt) => {
+    ($idx:expr, $dest:expr, $src:expr, $bots:expr, $op:ident) => {
         {
             let idx: usize = $idx;
             let src: Operand = $src;
             let dest: Operand = $dest;
             let bots: &mut Vec<Bot> = $bots;
 
-            let value = bots[idx].get(&dest) $op bots[idx].get(&src);
+            // registers are 16 bits wide, overflows wrap around like on the original
+            let value = bots[idx].get(&dest).$op(bots[idx].get(&src));
             bots[idx].put(&dest, value);
             bots[idx].energy -= 1;
             bots[idx].increment_ip();
@@ -267,12 +387,14 @@ macro_rules! simple_math_instr {
 
 // Bot Helpers
 impl Bot {
-    pub fn new(id: u16, position: Position) -> Bot {
+    pub fn new(id: u16, team: Option<usize>, position: Position, energy: u16) -> Bot {
         Bot {
             id,
+            team,
             position,
-            energy: 10000,
+            energy,
             sleeping: false,
+            mutations: 0,
 
             instruction_pointer: 0,
             stack_pointer: 3600,
@@ -290,30 +412,9 @@ impl Bot {
         }
     }
 
-    pub fn flash_drone(&mut self) {
-        let malicious: Vec<u16> = vec![
-            0x8004, 0x000F, 0x0000, 0x8004, 0x0018, 0x0000, 0x8004, 0x002A, 0x0000, 0x8004, 0x0045,
-            0x0000, 0x8006, 0xFFF7, 0x0000, 0x2020, 0x0DFB, 0x0004, 0x2020, 0x0DFC, 0x000A, 0x200F,
-            0x0DFC, 0x0001, 0x0005, 0x0000, 0x0000, 0x401E, 0x0002, 0x0000, 0x800E, 0x000F, 0x0000,
-            0x401A, 0x0002, 0x0000, 0x6017, 0x0002, 0x2710, 0x8009, 0x0006, 0x0000, 0x001F, 0x0000,
-            0x0000, 0x0005, 0x0000, 0x0000, 0x4001, 0x0000, 0x0DFB, 0x6020, 0x0001, 0x0DF8, 0x5024,
-            0x0000, 0x0001, 0x800E, 0x0012, 0x0000, 0x7017, 0x0000, 0x1000, 0x800B, 0x000C, 0x0000,
-            0x6020, 0x0001, 0x0E10, 0x7001, 0x0000, 0x1000, 0x1023, 0x0DFB, 0x0001, 0x0005, 0x0000,
-            0x0000, 0x2017, 0x0DFC, 0x0000, 0x800B, 0x000F, 0x0000, 0x001B, 0x0DFB, 0x0000, 0x800E,
-            0x0009, 0x0000, 0x2010, 0x0DFC, 0x0001, 0x0005, 0x0000, 0x0000, 0x8004, 0xFFAF, 0x0000,
-            0x8006, 0xFFEB, 0x0000,
-        ];
-
-        self.flash(malicious);
-    }
-
     pub fn get_glyph(&self) -> char {
         if !self.sleeping {
-            match self.id {
-                0..=26 => ((self.id + 64) as u8).into(),
-                27..=50 => ((self.id + 70) as u8).into(),
-                _ => '@',
-            }
+            Bot::glyph_from_id(self.id)
         } else {
             match self.id {
                 0..=50 => '.',
@@ -322,6 +423,22 @@ impl Bot {
         }
     }
 
+    // unlike the glyph, this is unique for drones too
+    pub fn get_name(&self) -> String {
+        match self.id {
+            1..=PLAYER_BOTS => Bot::glyph_from_id(self.id).to_string(),
+            _ => format!("@{}", self.id - PLAYER_BOTS),
+        }
+    }
+
+    pub fn glyph_from_id(id: u16) -> char {
+        match id {
+            0..=26 => ((id + 64) as u8).into(),
+            27..=50 => ((id + 70) as u8).into(),
+            _ => '@',
+        }
+    }
+
     pub fn id_from_glyph(glyph: char) -> u16 {
         match glyph as u8 {
             64..=90 => (glyph as u8 - 64).into(),
@@ -335,24 +452,25 @@ impl Bot {
     }
 
     pub fn is_occupied(pos: &Position, bots: &Vec<Bot>) -> bool {
-        Self::occupied_by(pos, bots) != 0xFFFF
+        Self::occupied_by(pos, bots).is_some()
     }
 
-    pub fn occupied_by(pos: &Position, bots: &Vec<Bot>) -> u16 {
-        for bot in bots {
-            if bot.position == *pos {
-                return bot.id - 1;
-            }
-        }
-        0xFFFFu16
+    // the index into `bots`, IDs can't be used for that since teams and drones leave gaps between them
+    pub fn occupied_by(pos: &Position, bots: &[Bot]) -> Option<usize> {
+        bots.iter().position(|bot| bot.position == *pos)
     }
 
-    pub fn travel(idx: usize, dir: u16, tank: &Tank, bots: &mut Vec<Bot>) -> bool {
+    pub fn travel(idx: usize, dir: u16, tank: &mut Tank, bots: &mut Vec<Bot>, cost: u16) -> bool {
         let mut new_position = bots[idx].position.clone();
         let in_bounds: bool = tank.check_direction(dir, &mut new_position);
+        let blocked = !in_bounds || Bot::is_occupied(&new_position, bots);
+
+        if let (true, Some(heatmaps)) = (blocked, &mut tank.heatmaps) {
+            heatmaps.add(HeatmapKind::Blocked, &bots[idx].position, 1);
+        }
 
-        if in_bounds && !Bot::is_occupied(&new_position, bots) && bots[idx].has_energy(10) {
-            bots[idx].energy -= 10;
+        if !blocked && bots[idx].has_energy(cost) {
+            bots[idx].energy -= cost;
             bots[idx].position = new_position;
             return true
         } else if bots[idx].has_energy(1) {
@@ -362,6 +480,31 @@ impl Bot {
 
         false
     }
+
+    // same energy rules as travel, the ramp decides where the bot ends up
+    pub fn climb(idx: usize, tank: &mut Tank, bots: &mut Vec<Bot>, cost: u16) -> bool {
+        let destination = tank.ramp_destination(&bots[idx].position);
+
+        if let (Some(destination), Some(heatmaps)) = (destination, &mut tank.heatmaps) {
+            if Bot::is_occupied(&destination, bots) {
+                heatmaps.add(HeatmapKind::Blocked, &bots[idx].position, 1);
+            }
+        }
+
+        match destination {
+            Some(new_position) if !Bot::is_occupied(&new_position, bots) && bots[idx].has_energy(cost) => {
+                bots[idx].energy -= cost;
+                bots[idx].position = new_position;
+                true
+            }
+            _ => {
+                if bots[idx].has_energy(1) {
+                    bots[idx].energy -= 1;
+                }
+                false
+            }
+        }
+    }
 }
 
 // Bot CPU
@@ -376,7 +519,7 @@ impl Bot {
         self.set_instruction_pointer(self.instruction_pointer + 3)
     }
 
-    fn get_instruction(&self) -> [u16; 3] {
+    pub fn get_instruction(&self) -> [u16; 3] {
         let end = (self.instruction_pointer as usize) + 3;
         let slice = &self.program_memory[(self.instruction_pointer as usize)..end];
         <[u16; 3]>::try_from(slice).expect("Instruction should have exactly 3 words")
@@ -411,7 +554,7 @@ impl Bot {
                 self.get(
                     &Operand::Direct(
                         Value::Number(match operator {
-                            PlusMinus::Plus => register_value + offset_value,
+                            PlusMinus::Plus => register_value.wrapping_add(offset_value),
                             PlusMinus::Minus => register_value.wrapping_sub(offset_value)
                         })
                     )
@@ -435,10 +578,8 @@ impl Bot {
                 Register::SP => self.stack_pointer = value,
                 _ => self.registers[u16::from(reg.clone()) as usize] = value,
             },
-            Operand::ImmediateValue(_) => {
-                // eprintln!("{}", self.stack_pointer);
-                panic!("Attempt to put something into immediate value");
-            }
+            // mutated code can do this, the write goes nowhere
+            Operand::ImmediateValue(_) => {}
             Operand::RegisterIndexedDirect(base, operator, offset) => {
                 let register_value = self.get(base.as_ref());
                 let offset_value = self.get(offset.as_ref());
@@ -446,7 +587,7 @@ impl Bot {
                 self.put(
                     &Operand::Direct(
                         Value::Number(match operator {
-                            PlusMinus::Plus => register_value + offset_value,
+                            PlusMinus::Plus => register_value.wrapping_add(offset_value),
                             PlusMinus::Minus => register_value.wrapping_sub(offset_value)
                         })
                     ),
@@ -470,16 +611,20 @@ impl Bot {
         }
     }
 
+    // corrupts a single random word of memory, the address is drawn before the value
     fn mutate(&mut self, rng: &mut Box<dyn RNGSystem>) {
-        let index = rng.rand(Some(self.program_memory.len() as u32)) as usize;
-        let value = rng.rand(Some(0x1_0000)) as u16;
+        let index = rng.rand("mutate_address", Some(self.program_memory.len() as u32 - 1)) as usize;
+        let value = rng.rand("mutate_value", Some(0xFFFF)) as u16;
         self.program_memory[index] = value;
+        self.mutations += 1;
     }
 
-    // TODO: this needs bounds handling, probably
+    // like `put`, pushing past the end of memory writes nothing, a full stack wraps SP around to 0xFFFF
     fn push(&mut self, value: u16) {
-        self.stack_pointer -= 1;
-        self.program_memory[self.stack_pointer as usize] = value;
+        self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+        if let Some(word) = self.program_memory.get_mut(self.stack_pointer as usize) {
+            *word = value;
+        }
     }
 
     // TODO: this needs bounds handling, probably
@@ -494,7 +639,8 @@ impl Bot {
         result
     }
 
-    pub fn tick(idx: usize, tank: &mut Tank, bots: &mut Vec<Bot>, rng: &mut Box<dyn RNGSystem>) {
+    // opcodes of the extended instruction set are executed as NOP unless the scenario enables them
+    pub fn tick(idx: usize, tank: &mut Tank, bots: &mut Vec<Bot>, rng: &mut Box<dyn RNGSystem>, scenario: &Scenario) {
         // i'm tired
         if bots[idx].energy < 1 {
             bots[idx].sleeping = true;
@@ -508,7 +654,7 @@ impl Bot {
         let instruction_id = instruction[0] & 0xFF;
 
         //println!("{}", Disassembler::parse(instruction, bots[idx].instruction_pointer, false));
-        if instruction_id <= InstructionType::CKSUM as u16 {
+        if instruction_id <= InstructionType::last_opcode(scenario.extended) {
             let op1_value = instruction[1];
             let op2_value = instruction[2];
 
@@ -602,13 +748,13 @@ impl Bot {
                     Bot::op_jns(idx, op1, bots);
                 }
                 InstructionType::ADD => {
-                    simple_math_instr!(idx, op1, op2, bots, +);
+                    simple_math_instr!(idx, op1, op2, bots, wrapping_add);
                 }
                 InstructionType::SUB => {
-                    simple_math_instr!(idx, op1, op2, bots, -);
+                    simple_math_instr!(idx, op1, op2, bots, wrapping_sub);
                 }
                 InstructionType::MULT => {
-                    simple_math_instr!(idx, op1, op2, bots, *);
+                    simple_math_instr!(idx, op1, op2, bots, wrapping_mul);
                 }
                 InstructionType::DIV => {
                     Bot::op_div(idx, op1, op2, bots);
@@ -617,13 +763,13 @@ impl Bot {
                     Bot::op_mod(idx, op1, op2, bots);
                 }
                 InstructionType::AND => {
-                    simple_math_instr!(idx, op1, op2, bots, &);
+                    simple_math_instr!(idx, op1, op2, bots, bitand);
                 }
                 InstructionType::OR => {
-                    simple_math_instr!(idx, op1, op2, bots, |);
+                    simple_math_instr!(idx, op1, op2, bots, bitor);
                 }
                 InstructionType::XOR => {
-                    simple_math_instr!(idx, op1, op2, bots, ^);
+                    simple_math_instr!(idx, op1, op2, bots, bitxor);
                 }
                 InstructionType::CMP => {
                     Bot::op_cmp(idx, op1, op2, bots);
@@ -638,22 +784,22 @@ impl Bot {
                     Bot::op_energy(idx, op1, bots);
                 }
                 InstructionType::TRAVEL => {
-                    Bot::op_travel(idx, op1, tank, bots);
+                    Bot::op_travel(idx, op1, tank, bots, scenario.travel_cost);
                 }
                 InstructionType::SHL => {
                     Bot::op_shl(idx, op1, op2, bots);
                 }
                 InstructionType::SHR => {
-                    Bot::op_shl(idx, op1, op2, bots);
+                    Bot::op_shr(idx, op1, op2, bots);
                 }
                 InstructionType::SENSE => {
                     Bot::op_sense(idx, op1, tank, bots);
                 }
                 InstructionType::EAT => {
-                    Bot::op_eat(idx, tank, rng, bots);
+                    Bot::op_eat(idx, tank, rng, bots, scenario.eat_reward);
                 }
                 InstructionType::RAND => {
-                    Bot::op_rand(idx, op1, op2, rng, bots);
+                    Bot::op_rand(idx, op1, op2, rng, bots, scenario.compat);
                 }
                 InstructionType::RELEASE => {
                     Bot::op_release(idx, op1, tank, bots);
@@ -667,9 +813,18 @@ impl Bot {
                 InstructionType::PEEK => {
                     Bot::op_peek(idx, op1, op2, tank, bots);
                 }
-                // InstructionType::CKSUM => {},
-                // not an instruction, do nothing
-                _ => Bot::op_nop(idx, bots)
+                InstructionType::CKSUM => {
+                    Bot::op_cksum(idx, op1, op2, bots);
+                }
+                InstructionType::GETID => {
+                    Bot::op_getid(idx, op1, bots);
+                }
+                InstructionType::LEVEL => {
+                    Bot::op_level(idx, op1, bots);
+                }
+                InstructionType::CLIMB => {
+                    Bot::op_climb(idx, tank, bots, scenario.travel_cost);
+                }
             };
         } else {
             Bot::op_nop(idx, bots);
@@ -878,11 +1033,11 @@ impl Bot {
         bots[idx].increment_ip();
     }
 
-    fn op_travel(idx: usize, direction: Operand, tank: &Tank, bots: &mut Vec<Bot>)  {
+    fn op_travel(idx: usize, direction: Operand, tank: &mut Tank, bots: &mut Vec<Bot>, cost: u16)  {
         let bot = &mut bots[idx];
         let direction = bot.get(&direction);
 
-        let success = Bot::travel(idx, direction, tank, bots);
+        let success = Bot::travel(idx, direction, tank, bots, cost);
         bots[idx].flags.success = success;
 
         bots[idx].increment_ip();
@@ -915,7 +1070,11 @@ impl Bot {
         let tile = tank.get_item(pos);
         match tile {
             Some(tile) => {
-                bots[idx].put(&dest, tile.id);
+                let id = match tile.item_type {
+                    ItemType::Ramp => RAMP_ID,
+                    _ => tile.id,
+                };
+                bots[idx].put(&dest, id);
                 bots[idx].flags.success = true;
             }
             None => {
@@ -928,11 +1087,11 @@ impl Bot {
         bots[idx].increment_ip();
     }
 
-    fn op_eat(idx: usize, tank: &mut Tank, rng: &mut Box<dyn RNGSystem>, bots: &mut Vec<Bot>) {
+    fn op_eat(idx: usize, tank: &mut Tank, rng: &mut Box<dyn RNGSystem>, bots: &mut Vec<Bot>, reward: u16) {
         let pos = &bots[idx].position;
         let current_energy = bots[idx].energy;
 
-        if current_energy + 2000 > 0xFFFF {
+        if current_energy > 0xFFFF - reward {
             bots[idx].flags.success = false;
         } else {
             let tile = tank.get_item(pos);
@@ -943,7 +1102,7 @@ impl Bot {
                             bots[idx].mutate(rng);
                         }
                         bots[idx].flags.success = true;
-                        bots[idx].energy += 2000;
+                        bots[idx].energy += reward;
                     }
                     _ => bots[idx].flags.success = false,
                 },
@@ -957,15 +1116,21 @@ impl Bot {
         bots[idx].increment_ip();
     }
 
+    // the original excludes `max` from the results, a `max` of 0 always yields 0
     fn op_rand(
         idx: usize,
         to: Operand,
         max: Operand,
         rng: &mut Box<dyn RNGSystem>,
         bots: &mut Vec<Bot>,
+        exclusive: bool,
     ) {
         let max = bots[idx].get(&max);
-        let result = rng.rand(Some(max as u32)) as u16;
+        let result = match (exclusive, max) {
+            (true, 0) => 0,
+            (true, max) => rng.rand("RAND", Some(max as u32 - 1)) as u16,
+            (false, max) => rng.rand("RAND", Some(max as u32)) as u16,
+        };
 
         bots[idx].put(&to, result);
 
@@ -978,11 +1143,13 @@ impl Bot {
         let current_energy = bots[idx].energy;
         let amount = bots[idx].get(&amount);
 
-        if current_energy + 1 < amount {
+        // the instruction costs 1 on top of the amount
+        if amount >= current_energy {
             bots[idx].flags.success = false;
         } else {
             bots[idx].energy -= amount;
-            bots[idx].flags.success = tank.deposit(amount, &pos);
+            let team = bots[idx].team;
+            bots[idx].flags.success = tank.deposit(amount, &pos, team);
         }
 
         bots[idx].energy -= 1;
@@ -1001,7 +1168,8 @@ impl Bot {
 
         let amount = bots[idx].get(&amount);
 
-        if current_energy + 1 < amount {
+        // the instruction costs 1 on top of the amount
+        if amount >= current_energy {
             bots[idx].flags.success = false;
         } else {
             let direction = bots[idx].get(&direction);
@@ -1012,11 +1180,10 @@ impl Bot {
             }
 
             if pos != new_position {
-                let other_bot_idx = Bot::occupied_by(&new_position, bots) as usize;
-                if other_bot_idx != 0xFFFF {
+                if let Some(other_bot_idx) = Bot::occupied_by(&new_position, bots) {
                     let other_bot_energy = bots[other_bot_idx].energy;
 
-                    if other_bot_energy + amount > 0xFFFF {
+                    if other_bot_energy.checked_add(amount).is_none() {
                         bots[idx].flags.success = false;
                     } else {
                         bots[idx].energy -= amount;
@@ -1040,9 +1207,9 @@ impl Bot {
         let mut new_position = bots[idx].position.clone();
 
         if tank.check_direction(direction, &mut new_position) {
-            let other_bot_idx = Bot::occupied_by(&new_position, bots) as usize;
-            if other_bot_idx != 0xFFFF {
-                let offset = bots[idx].get(&offset) as usize;
+            if let Some(other_bot_idx) = Bot::occupied_by(&new_position, bots) {
+                // mutated code can point anywhere, addresses wrap around the end of memory
+                let offset = bots[idx].get(&offset) as usize % bots[other_bot_idx].program_memory.len();
                 bots[other_bot_idx].program_memory[offset] = bots[idx].registers[0];
                 bots[idx].flags.success = true;
             } else {
@@ -1061,9 +1228,8 @@ impl Bot {
         let mut new_position = bots[idx].position.clone();
 
         if tank.check_direction(direction, &mut new_position) {
-            let other_bot_idx = Bot::occupied_by(&new_position, bots) as usize;
-            if other_bot_idx != 0xFFFF {
-                let offset = bots[idx].get(&offset) as usize;
+            if let Some(other_bot_idx) = Bot::occupied_by(&new_position, bots) {
+                let offset = bots[idx].get(&offset) as usize % bots[other_bot_idx].program_memory.len();
                 let value = bots[other_bot_idx].program_memory[offset];
                 bots[idx].put(&dest, value);
                 bots[idx].flags.success = true;
@@ -1078,15 +1244,60 @@ impl Bot {
         bots[idx].increment_ip();
     }
 
+    fn op_cksum(idx: usize, start: Operand, end: Operand, bots: &mut Vec<Bot>) {
+        let memory_size = bots[idx].program_memory.len();
+        let start_address = bots[idx].get(&start) as usize % memory_size;
+        // an end of 3600 is the end of memory, e.g. `cksum r8, 3600` sums up all of it
+        let end_address = match bots[idx].get(&end) as usize {
+            end if end == memory_size => memory_size,
+            end => end % memory_size,
+        };
+
+        // ranges that end before they start wrap around the end of memory
+        let length = match end_address >= start_address {
+            true => end_address - start_address,
+            false => end_address + memory_size - start_address,
+        };
+
+        let mut checksum: u16 = 0;
+        for offset in 0..length {
+            let address = (start_address + offset) % memory_size;
+            checksum = checksum.wrapping_add(bots[idx].program_memory[address]);
+        }
+
+        bots[idx].put(&start, checksum);
+
+        bots[idx].energy -= 1;
+        bots[idx].increment_ip();
+    }
+
     // Extended Instruction Set (WIP)
 
     fn op_getid(idx: usize, dest: Operand, bots: &mut Vec<Bot>) {
         let id = bots[idx].id;
         bots[idx].put(&dest, id);
+
+        bots[idx].energy -= 1;
+        bots[idx].increment_ip();
+    }
+
+    fn op_level(idx: usize, dest: Operand, bots: &mut Vec<Bot>) {
+        let level = bots[idx].position.z as u16;
+        bots[idx].put(&dest, level);
+
+        bots[idx].energy -= 1;
+        bots[idx].increment_ip();
+    }
+
+    fn op_climb(idx: usize, tank: &mut Tank, bots: &mut Vec<Bot>, cost: u16) {
+        let success = Bot::climb(idx, tank, bots, cost);
+        bots[idx].flags.success = success;
+
+        bots[idx].increment_ip();
     }
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub x: u8,
     pub y: u8,
@@ -1120,37 +1331,68 @@ pub struct Emulator {
     pub bots: Vec<Bot>,
     pub iterations: u32,
     pub current_tick: u32,
+    pub tracer: Option<Tracer>,
+    pub recorder: Option<Recorder>,
+    pub profiler: Option<Profiler>,
+    pub scenario: Scenario,
 }
 
+/// Highest amount of bots controlled by players, drone IDs start right after them
+pub const PLAYER_BOTS: u16 = 50;
+
 impl Emulator {
-    pub fn new(bytecode: &Vec<u16>, iterations: u32, seed: u32, modern_rng: bool) -> Emulator {
+    // every program in `programs` is a team with its own slice of bot IDs
+    pub fn new(
+        programs: &[Vec<u16>],
+        drone_program: &[u16],
+        scenario: &Scenario,
+        iterations: u32,
+        rng: Box<dyn RNGSystem>,
+    ) -> Emulator {
         let mut emulator = Emulator {
-            rng: match modern_rng {
-                true => Box::new(ModernRNG::new(seed)),
-                false => Box::new(LegacyRNG::new(seed)),
-            },
-            tank: Tank::new(Position::new(70, 40, 1)),
+            rng,
+            tank: Tank::new(scenario.bounds(), programs.len()),
             bots: vec![],
             iterations,
             current_tick: 0,
+            tracer: None,
+            profiler: None,
+            recorder: None,
+            scenario: scenario.clone(),
         };
 
-        emulator.tank.initial_fill(200, &mut emulator.rng);
+        emulator.tank.initial_fill(scenario.sludge, &mut emulator.rng);
 
-        emulator.bots = Self::create_bots(bytecode, &emulator.tank, &mut emulator.rng);
+        emulator.bots = Self::create_bots(programs, drone_program, scenario, &emulator.tank, &mut emulator.rng);
 
         emulator
     }
 
-    pub fn create_bots(bytecode: &Vec<u16>, tank: &Tank, rng: &mut Box<dyn RNGSystem>) -> Vec<Bot> {
+    pub fn team_of(id: u16, teams: usize, player_bots: u16) -> usize {
+        (id as usize - 1) * teams / player_bots as usize
+    }
+
+    pub fn team_ids(team: usize, teams: usize, player_bots: u16) -> std::ops::RangeInclusive<u16> {
+        let first = (1..=player_bots).find(|id| Self::team_of(*id, teams, player_bots) == team).unwrap();
+        let last = (1..=player_bots).rfind(|id| Self::team_of(*id, teams, player_bots) == team).unwrap();
+        first..=last
+    }
+
+    pub fn create_bots(
+        programs: &[Vec<u16>],
+        drone_program: &[u16],
+        scenario: &Scenario,
+        tank: &Tank,
+        rng: &mut Box<dyn RNGSystem>,
+    ) -> Vec<Bot> {
         let mut bots: Vec<Bot> = vec![];
 
-        for id in 1..=50 {
+        for id in 1..=scenario.bots {
             let pos: Position = loop {
                 let pos = Position {
-                    x: rng.rand(Some((tank.bounds.x - 1) as u32)) as u8,
-                    y: rng.rand(Some((tank.bounds.y - 1) as u32)) as u8,
-                    z: rng.rand(Some((tank.bounds.z - 1) as u32)) as u8,
+                    x: rng.rand("bot_x", Some((tank.bounds.x - 1) as u32)) as u8,
+                    y: rng.rand("bot_y", Some((tank.bounds.y - 1) as u32)) as u8,
+                    z: tank.random_level("bot_z", rng),
                 };
 
                 if !Bot::is_occupied(&pos, &bots) {
@@ -1158,17 +1400,18 @@ impl Emulator {
                 }
             };
 
-            let mut bot = Bot::new(id, pos);
-            bot.flash(bytecode.clone());
+            let team = Self::team_of(id, programs.len(), scenario.bots);
+            let mut bot = Bot::new(id, Some(team), pos, scenario.energy);
+            bot.flash(programs[team].clone());
             bots.push(bot);
         }
 
-        for id in 1..=20 {
+        for id in 1..=scenario.drones {
             let pos: Position = loop {
                 let pos = Position {
-                    x: rng.rand(Some((tank.bounds.x - 1) as u32)) as u8,
-                    y: rng.rand(Some((tank.bounds.y - 1) as u32)) as u8,
-                    z: rng.rand(Some((tank.bounds.z - 1) as u32)) as u8,
+                    x: rng.rand("drone_x", Some((tank.bounds.x - 1) as u32)) as u8,
+                    y: rng.rand("drone_y", Some((tank.bounds.y - 1) as u32)) as u8,
+                    z: tank.random_level("drone_z", rng),
                 };
 
                 if !Bot::is_occupied(&pos, &bots) {
@@ -1176,18 +1419,491 @@ impl Emulator {
                 }
             };
 
-            let mut bot = Bot::new(id + 50, pos);
-            bot.flash_drone();
+            let mut bot = Bot::new(id + PLAYER_BOTS, None, pos, scenario.energy);
+            bot.flash(drone_program.to_vec());
             bots.push(bot);
         }
 
         bots
     }
 
+    pub fn mutated_bots(&self) -> Vec<&Bot> {
+        self.bots.iter().filter(|bot| bot.mutations > 0).collect()
+    }
+
     pub fn tick(&mut self) {
         for bot_idx in 0..self.bots.len() {
-            Bot::tick(bot_idx, &mut self.tank, &mut self.bots, &mut self.rng);
+            let trace = match &self.tracer {
+                Some(tracer) if tracer.is_traced(&self.bots[bot_idx]) => {
+                    Some(BotState::capture(&self.bots[bot_idx]))
+                }
+                _ => None,
+            };
+
+            // the only item a bot can take out of the tank is the sludge it sits on
+            let position = self.bots[bot_idx].position;
+            let had_item = self.recorder.is_some() && self.tank.has_item(&position);
+            let (address, energy) = (self.bots[bot_idx].instruction_pointer, self.bots[bot_idx].energy);
+            // decided by the energy the tick starts with, the flag is only updated during the tick
+            let sleeping = energy < 1;
+
+            Bot::tick(bot_idx, &mut self.tank, &mut self.bots, &mut self.rng, &self.scenario);
+
+            if let Some(profiler) = &mut self.profiler {
+                profiler.count(&self.bots[bot_idx], address, energy, sleeping);
+            }
+
+            let bot = &self.bots[bot_idx];
+            if let (false, Some(heatmaps)) = (bot.sleeping, &mut self.tank.heatmaps) {
+                heatmaps.add(HeatmapKind::Visits, &bot.position, 1);
+            }
+
+            if let (Some(tracer), Some(before)) = (&mut self.tracer, trace) {
+                tracer.log(self.current_tick, &before, &self.bots[bot_idx]);
+            }
+
+            if let Some(recorder) = &mut self.recorder {
+                if had_item && !self.tank.has_item(&position) {
+                    recorder.eaten(position);
+                }
+            }
         }
         self.current_tick += 1;
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_tick(&self.tank, &self.bots);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Compiler;
+    use crate::rng::LegacyRNG;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // PEEK and POKE with a register as the first operand and an immediate as the second
+    const PEEK_R0: u16 = 0x6000 | InstructionType::PEEK as u16;
+    const POKE_R1: u16 = 0x6000 | InstructionType::POKE as u16;
+    const CHARGE_R0: u16 = 0x6000 | InstructionType::CHARGE as u16;
+    const CKSUM_R0_R1: u16 = 0x5000 | InstructionType::CKSUM as u16;
+    const CKSUM_R0_IMMEDIATE: u16 = 0x6000 | InstructionType::CKSUM as u16;
+    const RELEASE_100: u16 = 0x8000 | InstructionType::RELEASE as u16;
+    const SENSE_R0: u16 = 0x4000 | InstructionType::SENSE as u16;
+    const EAT: u16 = InstructionType::EAT as u16;
+    const CLIMB: u16 = InstructionType::CLIMB as u16;
+    const SHR_R0: u16 = 0x6000 | InstructionType::SHR as u16;
+    // an immediate as the destination, only mutated code ends up with one
+    const MOV_IMMEDIATE_R0: u16 = 0x9000 | InstructionType::MOV as u16;
+    const ADD_R0: u16 = 0x6000 | InstructionType::ADD as u16;
+    const SUB_R1: u16 = 0x6000 | InstructionType::SUB as u16;
+    const MULT_R2: u16 = 0x6000 | InstructionType::MULT as u16;
+    // register indexed, [r1+offset] is encoded as 0x1000 | offset
+    const MOV_INDEXED_IMMEDIATE: u16 = 0xE000 | InstructionType::MOV as u16;
+    const MOV_R0_INDEXED: u16 = 0x7000 | InstructionType::MOV as u16;
+    const PUSH_IMMEDIATE: u16 = 0x8000 | InstructionType::PUSH as u16;
+    const POP_R0: u16 = 0x4000 | InstructionType::POP as u16;
+
+    // a single level tank with one bot per program, in the given spots
+    fn setup(bots: &[(Position, Vec<u16>)]) -> (Tank, Vec<Bot>, Box<dyn RNGSystem>) {
+        let tank = Tank::new(Position::new(10, 10, 1), 1);
+        let bots = bots
+            .iter()
+            .enumerate()
+            .map(|(index, (position, program))| {
+                let mut bot = Bot::new(index as u16 + 1, Some(0), *position, 1000);
+                bot.flash(program.clone());
+                bot
+            })
+            .collect();
+
+        (tank, bots, Box::new(LegacyRNG::new(1)))
+    }
+
+    #[test]
+    fn peek_and_poke_wrap_mutated_offsets() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[
+            (Position::new(5, 5, 0), vec![PEEK_R0, 0, 31461, POKE_R1, 1, 31462]),
+            (Position::new(5, 6, 0), vec![]),
+        ]);
+        bots[0].registers[0] = 1; // down, towards the other bot
+        bots[0].registers[1] = 1;
+        bots[1].program_memory[31461 % 3600] = 1234;
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert!(bots[0].flags.success);
+        assert_eq!(bots[0].registers[0], 1234);
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert!(bots[0].flags.success);
+        assert_eq!(bots[1].program_memory[31462 % 3600], 1234);
+    }
+
+    #[test]
+    fn charge_finds_drones_next_to_a_short_bot_list() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[
+            (Position::new(5, 5, 0), vec![CHARGE_R0, 0, 100]),
+            (Position::new(5, 6, 0), vec![]),
+        ]);
+        // the first drone, right after a single player bot
+        bots[1].id = PLAYER_BOTS + 1;
+        bots[1].team = None;
+        bots[0].registers[0] = 1;
+
+        assert_eq!(Bot::occupied_by(&Position::new(5, 6, 0), &bots), Some(1));
+        assert_eq!(Bot::occupied_by(&Position::new(6, 6, 0), &bots), None);
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert!(bots[0].flags.success);
+        assert_eq!(bots[0].energy, 1000 - 100 - 1);
+        assert_eq!(bots[1].energy, 1000 + 100);
+    }
+
+    #[test]
+    fn shr_shifts_right() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(Position::new(5, 5, 0), vec![SHR_R0, 0, 2])]);
+        bots[0].registers[0] = 0b1100;
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].registers[0], 0b11);
+    }
+
+    #[test]
+    fn writes_into_immediates_are_ignored() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(Position::new(5, 5, 0), vec![MOV_IMMEDIATE_R0, 7, 0])]);
+        bots[0].registers[0] = 42;
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].instruction_pointer, 3);
+        assert_eq!(bots[0].energy, 999);
+        assert_eq!(bots[0].program_memory[..3], [MOV_IMMEDIATE_R0, 7, 0]);
+    }
+
+    #[test]
+    fn math_wraps_around() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(
+            Position::new(5, 5, 0),
+            vec![ADD_R0, 0, 2, SUB_R1, 1, 2, MULT_R2, 2, 2],
+        )]);
+        bots[0].registers[0] = 0xFFFF;
+        bots[0].registers[1] = 1;
+        bots[0].registers[2] = 0x8001;
+
+        for _ in 0..3 {
+            Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        }
+        assert_eq!(bots[0].registers[..3], [1, 0xFFFF, 2]);
+    }
+
+    #[test]
+    fn indexed_addresses_wrap_around() {
+        let scenario = Scenario::default();
+        // mov [r1+2], 7 and mov r0, [r1+5]
+        let (mut tank, mut bots, mut rng) = setup(&[(
+            Position::new(5, 5, 0),
+            vec![MOV_INDEXED_IMMEDIATE, 0x1002, 7, MOV_R0_INDEXED, 0, 0x1005],
+        )]);
+        bots[0].registers[1] = 0xFFFF;
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].program_memory[1], 7);
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].registers[0], bots[0].program_memory[4]);
+    }
+
+    #[test]
+    fn release_and_charge_leave_energy_for_themselves() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[
+            (Position::new(5, 5, 0), vec![RELEASE_100, 101, 0, RELEASE_100, 100, 0, RELEASE_100, 99, 0]),
+            (Position::new(1, 1, 0), vec![CHARGE_R0, 0, 101, CHARGE_R0, 0, 100, CHARGE_R0, 0, 99]),
+            (Position::new(1, 2, 0), vec![]),
+        ]);
+        bots[1].registers[0] = 1; // down, towards the third bot
+
+        for expected in [100, 99] {
+            bots[0].energy = expected;
+            bots[1].energy = expected;
+            for idx in 0..2 {
+                Bot::tick(idx, &mut tank, &mut bots, &mut rng, &scenario);
+                assert!(!bots[idx].flags.success);
+                assert_eq!(bots[idx].energy, expected - 1);
+            }
+        }
+
+        // 99 out of 100 leaves just enough for the instruction
+        bots[0].energy = 100;
+        bots[1].energy = 100;
+        for idx in 0..2 {
+            Bot::tick(idx, &mut tank, &mut bots, &mut rng, &scenario);
+            assert_eq!(bots[idx].energy, 0);
+        }
+        assert_eq!(bots[2].energy, 1000 + 99);
+    }
+
+    #[test]
+    fn push_on_a_full_stack_writes_nothing() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(Position::new(5, 5, 0), vec![PUSH_IMMEDIATE, 5, 0, POP_R0, 0, 0])]);
+        bots[0].stack_pointer = 0;
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].stack_pointer, 0xFFFF);
+        assert_eq!(bots[0].program_memory[..3], [PUSH_IMMEDIATE, 5, 0]);
+
+        bots[0].registers[0] = 42;
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].registers[0], 0);
+    }
+
+    #[test]
+    fn random_memory_never_panics() {
+        let scenario = Scenario {
+            extended: true,
+            ..Scenario::default()
+        };
+
+        for seed in 1..=20 {
+            let mut words = LegacyRNG::new(seed);
+            let program = (0..3600).map(|_| words.rand("word", Some(0xFFFF)) as u16).collect();
+            let (mut tank, mut bots, mut rng) = setup(&[(Position::new(5, 5, 0), program)]);
+            bots[0].energy = 0xFFFF;
+
+            for _ in 0..5000 {
+                Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+            }
+        }
+    }
+
+    #[test]
+    fn single_level_tanks_draw_no_level() {
+        let tank = Tank::new(Position::new(10, 10, 1), 1);
+        let mut rng: Box<dyn RNGSystem> = Box::new(LegacyRNG::new(3));
+        let mut expected = LegacyRNG::new(3);
+
+        let position = tank.get_random_position(&mut rng);
+        let x = expected.rand("item_x", Some(9)) as u8;
+        let y = expected.rand("item_y", Some(9)) as u8;
+
+        assert_eq!(position, Position::new(x, y, 0));
+        assert_eq!(rng.rand("next", None), expected.rand("next", None));
+    }
+
+    #[test]
+    fn cksum_wraps_around_the_end_of_memory() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(Position::new(5, 5, 0), vec![CKSUM_R0_R1, 0, 1])]);
+        bots[0].program_memory[3598] = 0xFFFF;
+        bots[0].program_memory[3599] = 2;
+        bots[0].registers[0] = 3598;
+        bots[0].registers[1] = 3600 + 2; // addresses past the end start over at 0
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+
+        // [3598, 2) holds 0xFFFF, 2 and the first two words of the CKSUM itself
+        let expected = 0xFFFFu16.wrapping_add(2).wrapping_add(CKSUM_R0_R1).wrapping_add(0);
+        assert_eq!(bots[0].registers[0], expected);
+        assert_eq!(bots[0].energy, 999);
+        assert_eq!(bots[0].instruction_pointer, 3);
+    }
+
+    #[test]
+    fn cksum_up_to_3600_sums_up_all_of_memory() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(Position::new(5, 5, 0), vec![CKSUM_R0_IMMEDIATE, 0, 3600])]);
+        for (address, word) in bots[0].program_memory.iter_mut().enumerate().skip(3) {
+            *word = (address * 7) as u16;
+        }
+        let expected = bots[0].program_memory.iter().fold(0u16, |sum, word| sum.wrapping_add(*word));
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].registers[0], expected);
+        assert_eq!(bots[0].energy, 999);
+    }
+
+    #[test]
+    fn cksum_of_an_empty_range_is_zero() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(Position::new(5, 5, 0), vec![CKSUM_R0_R1, 0, 1])]);
+        bots[0].registers[0] = 10;
+        bots[0].registers[1] = 10;
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].registers[0], 0);
+        assert_eq!(bots[0].energy, 999);
+    }
+
+    #[test]
+    fn releases_only_score_on_collection_points() {
+        let scenario = Scenario::default();
+        let release = vec![RELEASE_100, 100, 0];
+        let (mut tank, mut bots, mut rng) = setup(&[
+            (Position::new(1, 1, 0), release.clone()),
+            (Position::new(2, 2, 0), release.clone()),
+            (Position::new(3, 3, 0), release),
+        ]);
+        tank.add_item(ItemType::CollectionPoint, COLLECTION_POINT_ID, Position::new(1, 1, 0));
+        tank.add_item(ItemType::Sludge, 0, Position::new(2, 2, 0));
+
+        for idx in 0..3 {
+            Bot::tick(idx, &mut tank, &mut bots, &mut rng, &scenario);
+        }
+
+        assert_eq!(tank.score, 100);
+        assert_eq!(tank.team_scores, vec![100]);
+        assert!(bots[0].flags.success);
+        assert!(!bots[1].flags.success);
+        assert!(!bots[2].flags.success);
+        // the energy is gone either way
+        assert!(bots.iter().all(|bot| bot.energy == 1000 - 100 - 1));
+    }
+
+    #[test]
+    fn sense_reports_collection_points() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[(Position::new(1, 1, 0), vec![SENSE_R0, 0, 0])]);
+        tank.add_item(ItemType::CollectionPoint, COLLECTION_POINT_ID, Position::new(1, 1, 0));
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].registers[0], 0xFFFF);
+    }
+
+    #[test]
+    fn eating_toxic_sludge_mutates() {
+        let scenario = Scenario::default();
+        let (mut tank, mut bots, mut rng) = setup(&[
+            (Position::new(1, 1, 0), vec![EAT, 0, 0]),
+            (Position::new(2, 2, 0), vec![EAT, 0, 0]),
+        ]);
+        tank.toxic_sludge = vec![3];
+        tank.add_item(ItemType::Sludge, 4, Position::new(1, 1, 0));
+        tank.add_item(ItemType::Sludge, 3, Position::new(2, 2, 0));
+        let mut expected = LegacyRNG::new(1);
+
+        // harmless sludge draws nothing
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].mutations, 0);
+        assert_eq!(bots[0].energy, 1000 + 2000 - 1);
+
+        Bot::tick(1, &mut tank, &mut bots, &mut rng, &scenario);
+        assert!(bots[1].flags.success);
+        assert_eq!(bots[1].mutations, 1);
+        assert_eq!(bots[1].energy, 1000 + 2000 - 1);
+        assert!(!tank.has_item(&Position::new(2, 2, 0)));
+        assert_eq!(tank.sludge_eaten, 2);
+
+        // the address is drawn before the value
+        let address = expected.rand("mutate_address", Some(3599)) as usize;
+        let value = expected.rand("mutate_value", Some(0xFFFF)) as u16;
+        assert_eq!(bots[1].program_memory[address], value);
+        assert_eq!(rng.rand("next", None), expected.rand("next", None));
+    }
+
+    #[test]
+    fn ramps_link_neighbouring_levels() {
+        let mut tank = Tank::new(Position::new(10, 10, 3), 1);
+        let mut rng: Box<dyn RNGSystem> = Box::new(LegacyRNG::new(5));
+        tank.initial_fill(20, &mut rng);
+
+        let ramps: Vec<&Item> = tank
+            .elements
+            .iter()
+            .flatten()
+            .filter(|item| matches!(item.item_type, ItemType::Ramp))
+            .collect();
+        assert_eq!(ramps.len(), 2 * RAMPS_PER_LEVEL * 2);
+
+        for ramp in ramps {
+            let destination = tank.ramp_destination(&ramp.position).unwrap();
+            assert_eq!((destination.x, destination.y), (ramp.position.x, ramp.position.y));
+            assert_eq!(destination.z.abs_diff(ramp.position.z), 1);
+            assert_eq!(tank.ramp_destination(&destination), Some(ramp.position));
+        }
+    }
+
+    #[test]
+    fn climb_takes_ramps_unless_the_other_end_is_taken() {
+        let scenario = Scenario {
+            levels: 2,
+            extended: true,
+            ..Scenario::default()
+        };
+        let climb = vec![CLIMB, 0, 0, CLIMB, 0, 0];
+        let (_, mut bots, mut rng) = setup(&[
+            (Position::new(1, 1, 0), climb.clone()),
+            (Position::new(2, 2, 0), climb),
+            (Position::new(2, 2, 1), vec![]),
+        ]);
+        let mut tank = Tank::new(Position::new(10, 10, 2), 1);
+        for (x, y) in [(1, 1), (2, 2)] {
+            tank.add_item(ItemType::Ramp, 1, Position::new(x, y, 0));
+            tank.add_item(ItemType::Ramp, 0, Position::new(x, y, 1));
+        }
+
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert!(bots[0].flags.success);
+        assert_eq!(bots[0].position, Position::new(1, 1, 1));
+        assert_eq!(bots[0].energy, 1000 - scenario.travel_cost);
+
+        // and back down again
+        Bot::tick(0, &mut tank, &mut bots, &mut rng, &scenario);
+        assert_eq!(bots[0].position, Position::new(1, 1, 0));
+
+        Bot::tick(1, &mut tank, &mut bots, &mut rng, &scenario);
+        assert!(!bots[1].flags.success);
+        assert_eq!(bots[1].position, Position::new(2, 2, 0));
+        assert_eq!(bots[1].energy, 999);
+    }
+
+    // runs every "seed,bot,score" line of a score file in compatibility mode, returns the ones that differ
+    fn differing_scores(path: &str, iterations: u32) -> Vec<String> {
+        let expected_scores = fs::read_to_string(path).unwrap_or_else(|_| panic!("{path} is missing"));
+        let scenario = Scenario {
+            compat: true,
+            ..Scenario::default()
+        };
+
+        let mut failed = vec![];
+        for line in expected_scores.lines().skip(1).filter(|line| !line.trim().is_empty()) {
+            let fields: Vec<&str> = line.trim().split(',').collect();
+            let (seed, bot, expected) = (fields[0].parse().unwrap(), fields[1], fields[2].parse::<u64>().unwrap());
+
+            let compiler = Compiler::load(&PathBuf::from("bots").join(bot), false, false)
+                .unwrap_or_else(|error| panic!("{error}"));
+            let rng = Box::new(LegacyRNG::new(seed));
+            let mut emulator = Emulator::new(&[compiler.output], &DRONE_FIRMWARE, &scenario, iterations, rng);
+            while emulator.current_tick < emulator.iterations {
+                emulator.tick();
+            }
+
+            if emulator.tank.score != expected {
+                failed.push(format!("{bot}, seed {seed}: expected {expected}, got {}", emulator.tank.score));
+            }
+        }
+
+        failed
+    }
+
+    // recorded from the original with testing/record_scores.py
+    #[test]
+    #[ignore = "testing/truth/scores.csv has to be recorded with the original NANORGS executable first"]
+    fn compat_mode_matches_the_original_scores() {
+        let failed = differing_scores("testing/truth/scores.csv", 1_000_000);
+        assert!(failed.is_empty(), "{} of the original scores differ:\n{}", failed.len(), failed.join("\n"));
+    }
+
+    // recorded from OpenNANORGS itself with testing/record_compat_scores.py, rerun it for intended changes
+    #[test]
+    fn compat_mode_keeps_its_recorded_scores() {
+        let failed = differing_scores("testing/compat_scores.csv", 20000);
+        assert!(failed.is_empty(), "{} of the recorded scores differ:\n{}", failed.len(), failed.join("\n"));
     }
 }
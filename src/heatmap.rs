@@ -0,0 +1,230 @@
+use crate::emulator::Position;
+use ruscii::terminal::Color;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeatmapKind {
+    Visits,   // ticks an awake bot spent on the tile
+    Eaten,    // sludge eaten
+    Released, // energy released by RELEASE
+    Blocked,  // travels that ran into a bot or the tank's wall
+}
+
+impl HeatmapKind {
+    pub const ALL: [HeatmapKind; 4] = [
+        HeatmapKind::Visits,
+        HeatmapKind::Eaten,
+        HeatmapKind::Released,
+        HeatmapKind::Blocked,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeatmapKind::Visits => "visits",
+            HeatmapKind::Eaten => "eaten",
+            HeatmapKind::Released => "released",
+            HeatmapKind::Blocked => "blocked",
+        }
+    }
+
+    // none, then every map in order
+    pub fn next(kind: Option<HeatmapKind>) -> Option<HeatmapKind> {
+        match kind {
+            None => Some(HeatmapKind::Visits),
+            Some(HeatmapKind::Visits) => Some(HeatmapKind::Eaten),
+            Some(HeatmapKind::Eaten) => Some(HeatmapKind::Released),
+            Some(HeatmapKind::Released) => Some(HeatmapKind::Blocked),
+            Some(HeatmapKind::Blocked) => None,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            HeatmapKind::Visits => "visit frequency",
+            HeatmapKind::Eaten => "sludge eaten",
+            HeatmapKind::Released => "energy released",
+            HeatmapKind::Blocked => "collisions and failed travels",
+        }
+    }
+}
+
+/// Dark red over red to yellow, from cold to hot
+const PALETTE: [u8; 10] = [52, 88, 124, 160, 196, 202, 208, 214, 220, 226];
+
+/// Per tile counters gathered during the run, every level is a separate layer
+#[derive(Debug, Clone)]
+pub struct Heatmaps {
+    bounds: Position,
+    counts: [Vec<u64>; 4], // indexed by HeatmapKind
+}
+
+impl Heatmaps {
+    pub fn new(bounds: Position) -> Heatmaps {
+        let tiles = bounds.x as usize * bounds.y as usize * bounds.z as usize;
+        Heatmaps {
+            bounds,
+            counts: std::array::from_fn(|_| vec![0; tiles]),
+        }
+    }
+
+    fn index(&self, position: &Position) -> usize {
+        let (width, height) = (self.bounds.x as usize, self.bounds.y as usize);
+        position.x as usize + position.y as usize * width + position.z as usize * width * height
+    }
+
+    pub fn add(&mut self, kind: HeatmapKind, position: &Position, amount: u64) {
+        let index = self.index(position);
+        self.counts[kind as usize][index] += amount;
+    }
+
+    pub fn get(&self, kind: HeatmapKind, position: &Position) -> u64 {
+        self.counts[kind as usize][self.index(position)]
+    }
+
+    pub fn max(&self, kind: HeatmapKind) -> u64 {
+        self.counts[kind as usize].iter().copied().max().unwrap_or(0)
+    }
+
+    /// How hot a tile is from 0 to 1, on a log scale so a few busy tiles don't drown out the rest,
+    /// `max` is the one of the whole map
+    pub fn heat(&self, kind: HeatmapKind, position: &Position, max: u64) -> f64 {
+        if max == 0 {
+            return 0.0;
+        }
+
+        (self.get(kind, position) as f64).ln_1p() / (max as f64).ln_1p()
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.bounds.z).flat_map(move |z| {
+            (0..self.bounds.y).flat_map(move |y| (0..self.bounds.x).map(move |x| Position::new(x, y, z)))
+        })
+    }
+
+    // untouched tiles keep the tank's background
+    pub fn color(&self, kind: HeatmapKind, position: &Position, max: u64) -> Option<Color> {
+        if self.get(kind, position) == 0 {
+            return None;
+        }
+
+        let heat = self.heat(kind, position, max);
+        let index = (heat * (PALETTE.len() - 1) as f64).round() as usize;
+        Some(Color::Xterm(PALETTE[index]))
+    }
+
+    // a .csv file gets every counter, anything else a PGM image for every counter next to it
+    pub fn export(&self, path: &Path) -> Result<Vec<PathBuf>, String> {
+        let is_csv = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+
+        match is_csv {
+            true => self.write_csv(path).map(|_| vec![path.to_path_buf()]),
+            false => HeatmapKind::ALL.iter().map(|kind| self.write_pgm(path, *kind)).collect(),
+        }
+    }
+
+    fn write_csv(&self, path: &Path) -> Result<(), String> {
+        let names: Vec<&str> = HeatmapKind::ALL.iter().map(|kind| kind.name()).collect();
+        let mut output = format!("x,y,z,{}\n", names.join(","));
+
+        for position in self.positions() {
+            let counts: Vec<String> = HeatmapKind::ALL
+                .iter()
+                .map(|kind| self.get(*kind, &position).to_string())
+                .collect();
+            output += &format!("{},{},{},{}\n", position.x, position.y, position.z, counts.join(","));
+        }
+
+        fs::write(path, output).map_err(|error| format!("couldn't write {}: {error}", path.display()))
+    }
+
+    // e.g. "heat.pgm" becomes "heat-visits.pgm", levels are stacked from top to bottom
+    fn write_pgm(&self, path: &Path, kind: HeatmapKind) -> Result<PathBuf, String> {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let path = path.with_file_name(format!("{}-{}.pgm", stem, kind.name()));
+
+        let height = self.bounds.y as usize * self.bounds.z as usize;
+        let max = self.max(kind);
+        let mut output = format!("P5\n{} {}\n255\n", self.bounds.x, height).into_bytes();
+        output.extend(self.positions().map(|position| (self.heat(kind, &position, max) * 255.0).round() as u8));
+
+        fs::write(&path, output).map_err(|error| format!("couldn't write {}: {error}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 wide, 2 high, 2 levels
+    fn heatmaps() -> Heatmaps {
+        Heatmaps::new(Position::new(3, 2, 2))
+    }
+
+    #[test]
+    fn counts_add_up_per_tile_and_kind() {
+        let mut heatmaps = heatmaps();
+        heatmaps.add(HeatmapKind::Visits, &Position::new(1, 1, 0), 2);
+        heatmaps.add(HeatmapKind::Visits, &Position::new(1, 1, 0), 3);
+        heatmaps.add(HeatmapKind::Visits, &Position::new(1, 1, 1), 7);
+        heatmaps.add(HeatmapKind::Released, &Position::new(1, 1, 0), 4);
+
+        assert_eq!(heatmaps.get(HeatmapKind::Visits, &Position::new(1, 1, 0)), 5);
+        assert_eq!(heatmaps.get(HeatmapKind::Visits, &Position::new(1, 1, 1)), 7);
+        assert_eq!(heatmaps.get(HeatmapKind::Visits, &Position::new(1, 0, 0)), 0);
+        assert_eq!(heatmaps.get(HeatmapKind::Released, &Position::new(1, 1, 0)), 4);
+        assert_eq!(heatmaps.get(HeatmapKind::Eaten, &Position::new(1, 1, 0)), 0);
+        assert_eq!(heatmaps.max(HeatmapKind::Visits), 7);
+        assert_eq!(heatmaps.max(HeatmapKind::Blocked), 0);
+    }
+
+    #[test]
+    fn csv_lists_every_tile_with_every_counter() {
+        let mut heatmaps = heatmaps();
+        heatmaps.add(HeatmapKind::Eaten, &Position::new(2, 0, 1), 1);
+        heatmaps.add(HeatmapKind::Blocked, &Position::new(0, 1, 0), 9);
+
+        let path = std::env::temp_dir().join("open_nanorgs_heatmap.csv");
+        assert_eq!(heatmaps.export(&path).unwrap(), vec![path.clone()]);
+
+        let csv = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + 3 * 2 * 2);
+        assert_eq!(lines[0], "x,y,z,visits,eaten,released,blocked");
+        assert_eq!(lines[1], "0,0,0,0,0,0,0");
+        assert_eq!(lines[4], "0,1,0,0,0,0,9");
+        assert_eq!(lines[9], "2,0,1,0,1,0,0");
+    }
+
+    #[test]
+    fn pgm_images_stack_the_levels_on_a_log_scale() {
+        let mut heatmaps = heatmaps();
+        heatmaps.add(HeatmapKind::Visits, &Position::new(0, 0, 0), 15);
+        heatmaps.add(HeatmapKind::Visits, &Position::new(2, 1, 1), 1);
+
+        let path = std::env::temp_dir().join("open_nanorgs_heatmap.pgm");
+        let paths = heatmaps.export(&path).unwrap();
+        let names: Vec<String> = paths.iter().map(|path| path.file_name().unwrap().to_string_lossy().into()).collect();
+        assert_eq!(
+            names,
+            [
+                "open_nanorgs_heatmap-visits.pgm",
+                "open_nanorgs_heatmap-eaten.pgm",
+                "open_nanorgs_heatmap-released.pgm",
+                "open_nanorgs_heatmap-blocked.pgm",
+            ]
+        );
+
+        // 3 wide, 2 levels of 2 rows each
+        let header = b"P5\n3 4\n255\n";
+        let image = fs::read(&paths[0]).unwrap();
+        assert_eq!(&image[..header.len()], header);
+        // the hottest tile is white, ln(2) / ln(16) of the way there is 64
+        assert_eq!(&image[header.len()..], [255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 64]);
+
+        // nothing counted, all black
+        let image = fs::read(&paths[1]).unwrap();
+        assert_eq!(&image[header.len()..], [0; 12]);
+    }
+}
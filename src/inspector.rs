@@ -0,0 +1,145 @@
+use crate::debugger::Debugger;
+use crate::disassembler::Disassembler;
+use crate::emulator::{Emulator, ItemType, Position};
+use ruscii::drawing::Pencil;
+use ruscii::keyboard::Key;
+use ruscii::spatial::Vec2;
+use ruscii::terminal::Color;
+
+/// Amount of stack entries shown, starting at the top
+const STACK_ENTRIES: usize = 6;
+
+/// Selected bot and a cursor to point at tiles with, the cursor follows the bot until it's moved
+pub struct Inspector {
+    pub bot_id: u16,
+    pub cursor: Position,
+    following: bool,
+}
+
+impl Inspector {
+    pub fn new(emulator: &Emulator) -> Inspector {
+        let bot = &emulator.bots[0];
+        Inspector {
+            bot_id: bot.id,
+            cursor: bot.position,
+            following: true,
+        }
+    }
+
+    // `level` is the one the cursor moves on
+    pub fn handle_key(&mut self, key: Key, emulator: &Emulator, level: u8) -> bool {
+        let bounds = emulator.tank.bounds();
+        let index = emulator.bots.iter().position(|bot| bot.id == self.bot_id).unwrap_or(0);
+
+        match key {
+            Key::LeftBracket | Key::RightBracket => {
+                let count = emulator.bots.len();
+                let next = match key {
+                    Key::LeftBracket => (index + count - 1) % count,
+                    _ => (index + 1) % count,
+                };
+                self.bot_id = emulator.bots[next].id;
+                self.following = true;
+            }
+            Key::H | Key::J | Key::K | Key::L => {
+                let mut cursor = self.cursor;
+                cursor.z = level;
+
+                match key {
+                    Key::H => cursor.x = cursor.x.saturating_sub(1),
+                    Key::L => cursor.x = (cursor.x + 1).min(bounds.x - 1),
+                    Key::K => cursor.y = cursor.y.saturating_sub(1),
+                    _ => cursor.y = (cursor.y + 1).min(bounds.y - 1),
+                }
+
+                // pointing at a bot picks it
+                if let Some(bot) = emulator.bots.iter().find(|bot| bot.position == cursor) {
+                    self.bot_id = bot.id;
+                }
+                self.cursor = cursor;
+                self.following = false;
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn update(&mut self, emulator: &Emulator) {
+        if !self.following {
+            return;
+        }
+
+        if let Some(bot) = emulator.bots.iter().find(|bot| bot.id == self.bot_id) {
+            self.cursor = bot.position;
+        }
+    }
+
+    // e.g. "Cursor 12,5,0: sludge type 3 (toxic), bot C"
+    pub fn describe_cursor(&self, emulator: &Emulator) -> String {
+        let position = self.cursor;
+
+        let item = match emulator.tank.get_item(&position) {
+            Some(item) => match item.item_type {
+                ItemType::Sludge if emulator.tank.is_toxic(item.id) => format!("sludge type {} (toxic)", item.id),
+                ItemType::Sludge => format!("sludge type {}", item.id),
+                ItemType::CollectionPoint => "collection point".to_string(),
+                ItemType::Ramp => format!("ramp to level {}", item.id + 1),
+            },
+            None => "empty".to_string(),
+        };
+
+        let bot = match emulator.bots.iter().find(|bot| bot.position == position) {
+            Some(bot) => format!(", bot {}", bot.get_name()),
+            None => String::new(),
+        };
+
+        format!("Cursor {},{},{}: {}{}", position.x, position.y, position.z, item, bot)
+    }
+
+    pub fn draw(&self, pencil: &mut Pencil, emulator: &Emulator, origin: Vec2) {
+        let bot = match emulator.bots.iter().find(|bot| bot.id == self.bot_id) {
+            Some(bot) => bot,
+            None => return,
+        };
+
+        let mut line = 0;
+        let mut draw_line = |pencil: &mut Pencil, text: &str| {
+            pencil.draw_text(text, origin + Vec2::y(line));
+            line += 1;
+        };
+
+        let owner = match bot.team {
+            Some(team) => format!("team {}", team + 1),
+            None => "drone".to_string(),
+        };
+
+        pencil.set_foreground(Color::Xterm(172));
+        draw_line(pencil, &format!("Inspecting {} [{}]", bot.get_name(), owner));
+
+        pencil.set_foreground(Color::White);
+        for state_line in Debugger::state_lines(bot) {
+            draw_line(pencil, &state_line);
+        }
+
+        // the stack grows down from the end of memory
+        let stack: Vec<String> = bot
+            .program_memory
+            .iter()
+            .skip(bot.stack_pointer as usize)
+            .take(STACK_ENTRIES)
+            .map(|value| format!("{:04X}", value))
+            .collect();
+        let stack = match stack.is_empty() {
+            true => "empty".to_string(),
+            false => stack.join(" "),
+        };
+        draw_line(pencil, &format!("Stack: {}", stack));
+
+        draw_line(pencil, "");
+
+        let disassembler = Disassembler::new(bot.program_memory.to_vec(), emulator.scenario.extended);
+        let ip = bot.instruction_pointer;
+        draw_line(pencil, &format!(" > {:04}  {}", ip, disassembler.parse_at(ip, true)));
+    }
+}
@@ -1,18 +1,45 @@
 #![allow(dead_code)]
 
+mod batch;
+mod breakpoints;
 mod cli;
+mod colors;
 mod compiler;
+mod debugger;
+mod diagnostic;
 mod disassembler;
 mod emulator;
+mod heatmap;
+mod inspector;
 mod parser;
+mod playback;
+mod profiler;
+mod replay;
+mod results;
 mod rng;
+mod scenario;
+mod snapshot;
 mod symbol_table;
 pub mod tokenizer;
+mod tournament;
+mod trace;
 
+use crate::breakpoints::{Breakpoint, Breakpoints};
 use crate::cli::Arguments;
+use crate::colors::TEAM_COLORS;
 use crate::compiler::Compiler;
+use crate::debugger::Debugger;
 use crate::disassembler::Disassembler;
-use crate::emulator::{Bot, Emulator, ItemType};
+use crate::emulator::{Bot, Emulator, ItemType, Position, DRONE_FIRMWARE};
+use crate::heatmap::{HeatmapKind, Heatmaps};
+use crate::inspector::Inspector;
+use crate::playback::Playback;
+use crate::profiler::{Profiler, Source};
+use crate::replay::{Recorder, Replay};
+use crate::results::Results;
+use crate::scenario::Scenario;
+use crate::snapshot::Snapshot;
+use crate::trace::Tracer;
 use byteorder::{BigEndian, WriteBytesExt};
 use clap::Parser as clapParse;
 use ruscii::app::{App, Config, State};
@@ -24,6 +51,7 @@ use ruscii::terminal::{Color, Window};
 use std::fs;
 use std::fs::File;
 use std::io::Write;
+use std::thread;
 use std::time::{Instant, SystemTime};
 
 fn main() {
@@ -39,187 +67,762 @@ fn main() {
         );
     }
 
-    let compiler = Compiler::new_from_file(&args.bot_path, args.verbose);
+    if let Some(path) = &args.replay_path {
+        match Replay::load(path) {
+            Ok(replay) => replay.play(),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let snapshot = match &args.resume_path {
+        Some(path) => match Snapshot::load(path) {
+            Ok(snapshot) => Some(snapshot),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // a resumed run keeps the rules it was started with
+    let mut scenario = match (&snapshot, &args.scenario_path) {
+        (Some(snapshot), _) => snapshot.scenario.clone(),
+        (None, Some(path)) => match Scenario::load(path) {
+            Ok(scenario) => scenario,
+            Err(error) => {
+                eprintln!("error: invalid scenario {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        },
+        (None, None) => Scenario::default(),
+    };
+
+    // options given on the command line win over the scenario
+    if let Some(drones) = args.drone_count {
+        scenario.drones = drones;
+    }
+    if let Some(levels) = args.levels {
+        scenario.levels = levels;
+    }
+    if let Some(rng) = args.rng {
+        scenario.rng = rng;
+        scenario.replay_rng = None;
+    }
+    if args.record_rng.is_some() {
+        scenario.record_rng = args.record_rng.clone();
+    }
+    if args.replay_rng.is_some() {
+        scenario.replay_rng = args.replay_rng.clone();
+    }
+    scenario.extended |= args.extended;
+
+    scenario.compat |= args.compat;
+
+    if scenario.compat && args.drone_path.is_some() {
+        eprintln!("error: compatibility mode only supports the stock drone firmware");
+        std::process::exit(1);
+    }
+
+    // tournament games are played head to head, two teams at a time
+    let teams = match &args.tournament_dir {
+        Some(_) if !args.solo => 2,
+        Some(_) => 1,
+        None => args.bot_paths.len(),
+    };
+
+    if let Err(error) = scenario.validate(teams) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+
+    if let Some(snapshot) = &snapshot {
+        if snapshot.teams() != args.bot_paths.len() {
+            eprintln!(
+                "error: the snapshot was taken with {} bot programs, got {}",
+                snapshot.teams(),
+                args.bot_paths.len()
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let mut compilers: Vec<Compiler> = vec![];
+    let mut failed = false;
+
+    // report problems with every file before giving up
+    for path in &args.bot_paths {
+        match Compiler::load(path, args.verbose, scenario.extended) {
+            Ok(compiler) => compilers.push(compiler),
+            Err(error) => {
+                eprintln!("{}\n", error);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
 
     if args.show_disassembly {
-        let disassembler = Disassembler::new(compiler.output.clone());
+        for (path, compiler) in args.bot_paths.iter().zip(&compilers) {
+            let disassembler = Disassembler::new(compiler.output.clone(), scenario.extended);
 
-        disassembler.print_disassembly(
-            (&args.bot_path)
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string(),
-        );
+            disassembler.print_disassembly(
+                path
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+                compiler.info.as_ref(),
+            );
+        }
         return;
     } else if args.dump_bytecode {
-        let file_path = format!("{}.bin", &args.bot_path.display());
-        let mut bytecode: File = File::create(&file_path).unwrap();
+        for (path, compiler) in args.bot_paths.iter().zip(&compilers) {
+            let file_path = format!("{}.bin", path.display());
+            let mut bytecode: File = File::create(&file_path).unwrap();
 
-        for value in &compiler.output {
-            bytecode.write_u16::<BigEndian>(*value).unwrap();
-        }
+            for value in &compiler.output {
+                bytecode.write_u16::<BigEndian>(*value).unwrap();
+            }
 
-        bytecode.flush().unwrap();
-        println!("saved to {}", &file_path);
+            bytecode.flush().unwrap();
+            println!("saved to {}", &file_path);
+        }
         return;
     } else if args.dump_bytecode_text {
         use std::fmt::Write;
 
-        let file_path = format!("{}.txt", &args.bot_path.display());
-        let mut output = String::new();
+        for (path, compiler) in args.bot_paths.iter().zip(&compilers) {
+            let file_path = format!("{}.txt", path.display());
+            let mut output = String::new();
 
-        let mut word_count = 0;
+            let mut word_count = 0;
 
-        for word in &compiler.output {
-            if word_count == 2 {
-                write!(&mut output, "{:04x}", word).unwrap();
-            } else {
-                write!(&mut output, "{:04x} ", word).unwrap();
-            }
+            for word in &compiler.output {
+                if word_count == 2 {
+                    write!(&mut output, "{:04x}", word).unwrap();
+                } else {
+                    write!(&mut output, "{:04x} ", word).unwrap();
+                }
 
-            word_count += 1;
+                word_count += 1;
 
-            if word_count == 3 {
-                word_count = 0;
+                if word_count == 3 {
+                    word_count = 0;
+                    write!(&mut output, "\n").unwrap();
+                }
+            }
+            if word_count != 3 {
                 write!(&mut output, "\n").unwrap();
             }
-        }
-        if word_count != 3 {
-            write!(&mut output, "\n").unwrap();
-        }
 
-        fs::write(&file_path, output).unwrap();
+            fs::write(&file_path, output).unwrap();
 
-        println!("saved to {}", &file_path);
+            println!("saved to {}", &file_path);
+        }
         return;
     } else if args.debug_bot.is_some() {
         let bot_char: char = args.debug_bot.unwrap();
-        // TODO: do this validation with clap instead
-        match bot_char {
-            'A'..='Z' | 'a'..='x' => {
-                println!("you asked to debug \"{}\"", &bot_char)
+        // only the letters of bots the scenario puts in the tank
+        let id = match bot_char.is_ascii() {
+            true => Bot::id_from_glyph(bot_char),
+            false => 0,
+        };
+        if !(1..=scenario.bots).contains(&id) {
+            eprintln!(
+                "error: invalid bot identifier \"{}\", the scenario has bots {} to {}",
+                bot_char,
+                Bot::glyph_from_id(1),
+                Bot::glyph_from_id(scenario.bots)
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let symbol_tables: Vec<_> = compilers.iter().map(|compiler| compiler.symbol_table.clone()).collect();
+
+    // points without a bot belong to the team of the debugged bot, or the first one
+    let debugged_team = match args.debug_bot.map(Bot::id_from_glyph) {
+        Some(id) if (1..=scenario.bots).contains(&id) => Emulator::team_of(id, symbol_tables.len(), scenario.bots),
+        _ => 0,
+    };
+
+    let mut points = vec![];
+    for spec in &args.breakpoints {
+        match Breakpoint::parse_breakpoint(spec, &symbol_tables, scenario.bots, debugged_team) {
+            Ok(point) => points.push(point),
+            Err(error) => {
+                println!("invalid breakpoint \"{}\": {}", spec, error);
+                return;
             }
-            _ => {
-                println!("invalid bot identifier \"{}\"", &bot_char)
+        }
+    }
+    for spec in &args.watchpoints {
+        match Breakpoint::parse_watchpoint(spec, &symbol_tables, scenario.bots, debugged_team) {
+            Ok(point) => points.push(point),
+            Err(error) => {
+                println!("invalid watchpoint \"{}\": {}", spec, error);
+                return;
             }
         }
     }
+    let mut breakpoints = Breakpoints::new(points);
 
-    let mut emulator = Emulator::new(&compiler.output, args.iterations, args.seed.unwrap(), false);
+    let programs: Vec<Vec<u16>> = compilers.iter().map(|compiler| compiler.output.clone()).collect();
+    // kept around for the labels in the profile
+    let drone_compiler = match &args.drone_path {
+        Some(path) => match Compiler::load(path, args.verbose, scenario.extended) {
+            Ok(compiler) => Some(compiler),
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let drone_program = match &drone_compiler {
+        Some(compiler) => compiler.output.clone(),
+        None => DRONE_FIRMWARE.to_vec(),
+    };
+
+    let seeds = match &args.seeds {
+        Some(spec) => match batch::parse_seeds(spec) {
+            Ok(seeds) => Some(seeds),
+            Err(error) => {
+                eprintln!("error: invalid seeds \"{}\": {}", spec, error);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let jobs = match args.jobs {
+        Some(jobs) => jobs as usize,
+        None => thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+    };
+
+    if let Some(directory) = &args.tournament_dir {
+        let seeds = seeds.unwrap_or((1..=10).collect());
+
+        let tournament = tournament::run(directory, args.solo, &drone_program, &scenario, args.iterations, &seeds, jobs);
+        match tournament {
+            Ok(tournament) => {
+                tournament.print_leaderboard();
+                if let Some(path) = &args.csv_path {
+                    if let Err(error) = tournament.write_csv(path) {
+                        eprintln!("error: {}", error);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(seeds) = seeds {
+        let now = Instant::now();
+        match batch::run(&programs, &drone_program, &scenario, args.iterations, &seeds, jobs) {
+            Ok(runs) => {
+                println!("{} seeds done in {}ms", runs.len(), now.elapsed().as_millis());
+                batch::print_summary(&runs);
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let emulator = match snapshot {
+        Some(snapshot) => snapshot.restore(args.iterations),
+        None => rng::from_scenario(&scenario, args.seed.unwrap())
+            .map(|rng| Emulator::new(&programs, &drone_program, &scenario, args.iterations, rng)),
+    };
+    let mut emulator = match emulator {
+        Ok(emulator) => emulator,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+    };
+    breakpoints.check(&emulator.bots);
+
+    if let Some(log_path) = &args.log_path {
+        // trace only the debugged bot if there is one, otherwise everyone
+        let traced_bot = args.debug_bot.map(Bot::id_from_glyph).filter(|id| *id > 0);
+        match Tracer::new(log_path, traced_bot, emulator.rng.get_seed(), scenario.extended) {
+            Ok(tracer) => emulator.tracer = Some(tracer),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = &args.save_replay_path {
+        let names = args
+            .bot_paths
+            .iter()
+            .zip(&compilers)
+            .map(|(path, compiler)| match &compiler.info {
+                Some(info) => info.to_string(),
+                None => path.display().to_string(),
+            })
+            .collect();
+
+        match Recorder::new(path, &emulator, &programs, &drone_program, names) {
+            Ok(recorder) => emulator.recorder = Some(recorder),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.profile_path.is_some() {
+        emulator.profiler = Some(Profiler::new(programs.len()));
+    }
+
+    // the UI shows them as overlays, otherwise they're only gathered if they're saved
+    if !args.quiet_mode || args.heatmap_path.is_some() {
+        emulator.tank.heatmaps = Some(Heatmaps::new(emulator.tank.bounds()));
+    }
+
+    // the JSON replaces the report so stdout stays parseable
+    let json_to_stdout = args.json_path.as_ref().is_some_and(|path| path.as_os_str() == "-");
 
     if args.quiet_mode {
         let now = Instant::now();
         while emulator.current_tick < emulator.iterations {
-            emulator.tick()
+            emulator.tick();
+
+            if breakpoints.is_empty() {
+                continue;
+            }
+
+            if let Some(hit) = breakpoints.check(&emulator.bots) {
+                println!("{} at tick {}", hit.description, emulator.current_tick);
+
+                let bot = emulator.bots.iter().find(|bot| bot.id == hit.bot_id).unwrap();
+                let disassembler = Disassembler::new(bot.program_memory.to_vec(), scenario.extended);
+                println!("{:04}  {}", bot.instruction_pointer, disassembler.parse_at(bot.instruction_pointer, true));
+                for line in Debugger::state_lines(bot) {
+                    println!("{}", line);
+                }
+                break;
+            }
+        }
+        if !json_to_stdout {
+            println!("done in {}ms", now.elapsed().as_millis())
         }
-        println!("done in {}ms", now.elapsed().as_millis())
     } else {
         let mut fps_counter = FPSCounter::default();
         let mut app = App::config(Config::fps(Config::new(), 60));
 
+        let mut debugger = args.debug_bot.map(|glyph| Debugger::new(Bot::id_from_glyph(glyph)));
+        let mut playback = Playback::new();
+        let mut inspector = Inspector::new(&emulator);
+
+        let tank_bounds = emulator.tank.bounds();
+        let mut level: u8 = 0;
+        let mut side_by_side = args.side_by_side && tank_bounds.z > 1;
+        let mut overlay: Option<HeatmapKind> = None;
+
         app.run(|app_state: &mut State, window: &mut Window| {
             // TODO: this is moderately annoying, figure out how to allow Ctrl+C
             for key_event in app_state.keyboard().last_key_events() {
                 match key_event {
                     KeyEvent::Pressed(Key::Esc) => app_state.stop(),
                     KeyEvent::Pressed(Key::Q) => app_state.stop(),
+                    KeyEvent::Pressed(Key::Tab) if tank_bounds.z > 1 => {
+                        side_by_side = false;
+                        level = (level + 1) % tank_bounds.z;
+                    }
+                    KeyEvent::Pressed(Key::V) if tank_bounds.z > 1 => side_by_side = !side_by_side,
+                    KeyEvent::Pressed(Key::M) => overlay = HeatmapKind::next(overlay),
+                    KeyEvent::Pressed(key) => {
+                        if playback.handle_key(*key, debugger.is_some()) {
+                            continue;
+                        }
+                        let cursor_level = if side_by_side { inspector.cursor.z } else { level };
+                        if inspector.handle_key(*key, &emulator, cursor_level) {
+                            // an attached debugger follows the selection
+                            if let Some(debugger) = &mut debugger {
+                                debugger.bot_id = inspector.bot_id;
+                            }
+                            continue;
+                        }
+                        if let Some(debugger) = &mut debugger {
+                            debugger.handle_key(*key, emulator.current_tick);
+                        }
+                    }
                     _ => (),
                 }
             }
 
             fps_counter.update();
 
-            if emulator.current_tick < emulator.iterations {
+            // an attached debugger decides how far to run, unless jumping to the end
+            let debugger_ticks = match &mut debugger {
+                Some(debugger) if !playback.is_jumping() => Some(debugger.ticks_to_run(emulator.current_tick)),
+                _ => None,
+            };
+
+            let started = Instant::now();
+            let mut ran = 0;
+
+            loop {
+                let keep_running = match debugger_ticks {
+                    Some(ticks) => ran < ticks,
+                    None => playback.keep_running(ran, started),
+                };
+                if !keep_running || emulator.current_tick >= emulator.iterations {
+                    break;
+                }
+
                 emulator.tick();
-            } else {
+                ran += 1;
+
+                if breakpoints.is_empty() {
+                    continue;
+                }
+
+                if let Some(hit) = breakpoints.check(&emulator.bots) {
+                    let message = format!("{} at tick {}", hit.description, emulator.current_tick);
+                    debugger
+                        .get_or_insert_with(|| Debugger::new(hit.bot_id))
+                        .break_at(hit.bot_id, message);
+                    playback.stop();
+                    break;
+                }
+            }
+
+            if emulator.current_tick >= emulator.iterations {
                 app_state.stop()
             }
 
+            inspector.update(&emulator);
+
             let mut pencil = Pencil::new(window.canvas_mut());
 
-            for element in &emulator.tank.elements {
-                let element = element.as_ref();
-                match element {
-                    Some(element) => {
-                        match element.item_type {
-                            ItemType::Sludge => pencil.set_foreground(Color::Grey),
-                            ItemType::CollectionPoint => pencil.set_foreground(Color::Xterm(6)),
-                            ItemType::Ramp => pencil.set_foreground(Color::DarkGrey),
-                        };
-
-                        pencil.draw_char(element.get_glyph(), element.position.into());
+            // levels are either drawn next to each other or one at a time
+            let columns = if side_by_side { tank_bounds.z as i32 } else { 1 };
+            let screen_position = |position: Position| -> Option<Vec2> {
+                if side_by_side {
+                    Some(Vec2::from(position) + Vec2::x(position.z as i32 * (tank_bounds.x as i32 + 1)))
+                } else if position.z == level {
+                    Some(position.into())
+                } else {
+                    None
+                }
+            };
+
+            pencil.set_foreground(Color::DarkGrey);
+            for column in 1..columns {
+                let x = column * (tank_bounds.x as i32 + 1) - 1;
+                for y in 0..tank_bounds.y as i32 {
+                    pencil.draw_char('|', Vec2::xy(x, y));
+                }
+            }
+
+            // with an overlay every tile gets its heat as the background, whatever is drawn on top keeps it
+            let background = *pencil.background();
+            let heatmap = overlay.zip(emulator.tank.heatmaps.as_ref());
+            let hottest = heatmap.map_or(0, |(kind, heatmaps)| heatmaps.max(kind));
+            let tile_background = |position: &Position| -> Color {
+                heatmap
+                    .and_then(|(kind, heatmaps)| heatmaps.color(kind, position, hottest))
+                    .unwrap_or(background)
+            };
+
+            if let Some((_, heatmaps)) = heatmap {
+                for position in heatmaps.positions() {
+                    if let Some(screen) = screen_position(position) {
+                        pencil.set_background(tile_background(&position));
+                        pencil.draw_char(' ', screen);
                     }
-                    None => {}
                 }
             }
 
-            let debug_bot_id = match args.debug_bot {
-                Some(glyph) => Bot::id_from_glyph(glyph),
+            // empty tiles are None
+            for element in emulator.tank.elements.iter().flatten() {
+                match element.item_type {
+                    ItemType::Sludge => pencil.set_foreground(Color::Grey),
+                    ItemType::CollectionPoint => pencil.set_foreground(Color::Xterm(6)),
+                    ItemType::Ramp => pencil.set_foreground(Color::DarkGrey),
+                };
+
+                if let Some(position) = screen_position(element.position) {
+                    pencil.set_background(tile_background(&element.position));
+                    pencil.draw_char(element.get_glyph(), position);
+                }
+            }
+
+            let debug_bot_id = match &debugger {
+                Some(debugger) => debugger.bot_id,
                 None => 0,
             };
 
             for bot in &emulator.bots {
+                let position = match screen_position(bot.position) {
+                    Some(position) => position,
+                    None => continue,
+                };
+
                 if debug_bot_id > 0 && bot.id == debug_bot_id {
                     pencil.set_foreground(Color::Xterm(172));
-                } else if bot.id > 50 {
-                    pencil.set_foreground(Color::Xterm(1));
+                } else if bot.mutations > 0 {
+                    pencil.set_foreground(Color::Xterm(5));
                 } else {
-                    pencil.set_foreground(Color::White);
+                    match bot.team {
+                        Some(team) => pencil.set_foreground(TEAM_COLORS[team % TEAM_COLORS.len()]),
+                        None => pencil.set_foreground(Color::Xterm(1)),
+                    };
                 }
 
-                pencil.draw_char(bot.get_glyph(), bot.position.into());
+                pencil.set_background(tile_background(&bot.position));
+                pencil.draw_char(bot.get_glyph(), position);
+            }
+            pencil.set_background(background);
+
+            // the cursor keeps whatever is drawn on its tile, on a lighter background
+            if let Some(position) = screen_position(inspector.cursor) {
+                let bot = emulator.bots.iter().find(|bot| bot.position == inspector.cursor);
+                let glyph = match (bot, emulator.tank.get_item(&inspector.cursor)) {
+                    (Some(bot), _) => bot.get_glyph(),
+                    (None, Some(item)) => item.get_glyph(),
+                    (None, None) => ' ',
+                };
+
+                pencil.set_foreground(Color::White).set_background(Color::Xterm(240));
+                pencil.draw_char(glyph, position);
+                pencil.set_background(background);
             }
 
+            let status_line = tank_bounds.y as i32;
+
             pencil.set_foreground(Color::White);
-            pencil.draw_text(&format!("FPS: {}", fps_counter.count()), Vec2::xy(0, 40));
-            pencil.draw_text(&format!("Seed: {}", &args.seed.unwrap()), Vec2::xy(0, 41));
+            pencil.draw_text(&format!("FPS: {}", fps_counter.count()), Vec2::xy(0, status_line));
+            if tank_bounds.z > 1 {
+                let shown = match side_by_side {
+                    true => format!("Levels: 1-{}", tank_bounds.z),
+                    false => format!("Level: {}/{}", level + 1, tank_bounds.z),
+                };
+                pencil.draw_text(&format!("{}  (tab: next level, v: side by side)", shown), Vec2::xy(12, status_line));
+            }
             pencil.draw_text(
-                &format!(
-                    "Bot[0] IP: {}, SP: {}, Energy: {}, Flags: {}",
-                    emulator.bots[0].instruction_pointer, emulator.bots[0].stack_pointer, emulator.bots[0].energy, emulator.bots[0].flags
-                ),
-                Vec2::xy(0, 42),
+                &format!("Seed: {}  Bot Info: {}", emulator.rng.get_seed(), format_info(&compilers)),
+                Vec2::xy(0, status_line + 1),
             );
+            pencil.draw_text(&inspector.describe_cursor(&emulator), Vec2::xy(0, status_line + 2));
+            pencil.set_foreground(Color::DarkGrey);
+            pencil.draw_text("[/]: previous/next bot  h/j/k/l: move the cursor", Vec2::xy(0, status_line + 3));
+            pencil.set_foreground(Color::White);
             pencil.draw_text(
-                &format!("Bot[0] Registers: {:?}", emulator.bots[0].registers),
-                Vec2::xy(0, 43),
+                &format!("Toxic Sludge: {:?} of {}", emulator.tank.toxic_sludge, emulator.tank.sludge_types),
+                Vec2::xy(0, status_line + 4)
             );
             pencil.draw_text(
-                &format!("Toxic Sludge: {:?} of {}", emulator.tank.toxic_sludge, emulator.tank.sludge_types),
-                Vec2::xy(0, 44)
+                &format!("Mutated bots: {}", format_mutated(&emulator)),
+                Vec2::xy(0, status_line + 5)
             );
+
+            if programs.len() > 1 {
+                let mut x = 0;
+                for (team, score) in emulator.tank.team_scores.iter().enumerate() {
+                    let text = format!("{}: {}  ", format_team(team, programs.len(), scenario.bots), score);
+                    pencil.set_foreground(TEAM_COLORS[team % TEAM_COLORS.len()]);
+                    pencil.draw_text(&text, Vec2::xy(x, status_line + 6));
+                    x += text.len() as i32;
+                }
+            }
+
+            pencil.set_foreground(Color::White);
+            pencil.draw_text(
+                &playback.status(emulator.current_tick, emulator.iterations),
+                Vec2::xy(0, status_line + 7),
+            );
+            pencil.set_foreground(Color::DarkGrey);
+            pencil.draw_text(
+                "space: pause  s: step  up/down: speed  end: jump to the end  m: overlay  q: quit",
+                Vec2::xy(0, status_line + 8),
+            );
+
+            if let Some(kind) = overlay {
+                pencil.set_foreground(Color::White);
+                pencil.draw_text(
+                    &format!("Overlay: {}, hottest tile {}", kind.description(), hottest),
+                    Vec2::xy(0, status_line + 9),
+                );
+            }
+
+            let panel = Vec2::xy(columns * (tank_bounds.x as i32 + 1) + 1, 0);
+            match &debugger {
+                Some(debugger) => debugger.draw(&mut pencil, &emulator, &breakpoints, panel),
+                None => inspector.draw(&mut pencil, &emulator, panel),
+            }
         });
     }
 
-    // TODO: change this condition to be after all ticks processed, regardless of amount
-    if emulator.current_tick == emulator.iterations {
-        let mut live_bots = 0;
-        let mut live_drones = 0;
-
-        for bot in &emulator.bots {
-            match bot.id {
-                1..=50 => {
-                    if !bot.sleeping {
-                        live_bots += 1
-                    }
-                }
-                _ => {
-                    if !bot.sleeping {
-                        live_drones += 1
-                    }
+    if let Some(tracer) = &mut emulator.tracer {
+        if let Err(error) = tracer.finish() {
+            eprintln!("error: couldn't write the trace: {}", error);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(recorder) = emulator.recorder.take() {
+        if let Err(error) = recorder.finish() {
+            eprintln!("error: couldn't write the replay: {}", error);
+            std::process::exit(1);
+        }
+    }
+
+    if let Err(error) = emulator.rng.finish() {
+        eprintln!("error: couldn't write the RNG recording: {}", error);
+        std::process::exit(1);
+    }
+
+    if let Some(path) = &args.snapshot_path {
+        let saved = Snapshot::capture(&emulator).and_then(|snapshot| snapshot.save(path));
+        if let Err(error) = saved {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+    }
+
+    if let (Some(path), Some(heatmaps)) = (&args.heatmap_path, &emulator.tank.heatmaps) {
+        match heatmaps.export(path) {
+            Ok(paths) if !json_to_stdout => {
+                for path in paths {
+                    println!("Heatmap saved to {}", path.display());
                 }
             }
+            Ok(_) => {}
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let (Some(path), Some(profiler)) = (&args.profile_path, &emulator.profiler) {
+        let mut sources: Vec<Source> = args
+            .bot_paths
+            .iter()
+            .zip(&compilers)
+            .map(|(path, compiler)| match &compiler.info {
+                Some(info) => Source::new(format!("{} ({})", info, path.display()), compiler),
+                None => Source::new(path.display().to_string(), compiler),
+            })
+            .collect();
+        sources.push(match (&args.drone_path, &drone_compiler) {
+            (Some(path), Some(compiler)) => Source::new(format!("the drones ({})", path.display()), compiler),
+            _ => Source::firmware("the drones".to_string(), drone_program.clone()),
+        });
+
+        if let Err(error) = profiler.save(path, &sources, scenario.extended) {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+        if !json_to_stdout {
+            println!("Profile saved to {}", path.display());
         }
+    }
 
-        println!("Bot Info: <not implemented>"); // TODO: grab info line
-        println!("Final score: {}", emulator.tank.score);
+    let results = Results::collect(&emulator, &args.bot_paths, &compilers);
+
+    // TODO: change this condition to be after all ticks processed, regardless of amount
+    if emulator.current_tick == emulator.iterations && !json_to_stdout {
+        println!("Bot Info: {}", format_info(&compilers));
+        println!("Final score: {}", results.score);
+        println!("Mutated bots: {}", format_mutated(&emulator));
         println!(
             "Live bots: {}, Live drones: {}, Seed: {}",
-            live_bots,
-            live_drones,
-            emulator.rng.get_seed()
-        )
+            results.live_bots, results.live_drones, results.seed
+        );
+
+        if programs.len() > 1 {
+            for (team, result) in results.teams.iter().enumerate() {
+                println!(
+                    "{} ({}): score {}, live bots {}",
+                    format_team(team, programs.len(), scenario.bots),
+                    result.path,
+                    result.score,
+                    result.live_bots
+                );
+            }
+        }
+    }
+
+    // written even if the run was stopped early, `ticks` tells how far it got
+    match &args.json_path {
+        Some(_) if json_to_stdout => println!("{}", results.to_json()),
+        Some(path) => {
+            if let Err(error) = fs::write(path, results.to_json()) {
+                eprintln!("error: couldn't write {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        }
+        None => {}
+    }
+
+    if json_to_stdout {
+        return;
+    }
+
+    if let Some(divergence) = &results.rng_divergence {
+        println!("RNG diverged from the recording: {}", divergence);
+    } else if scenario.replay_rng.is_some() {
+        println!("RNG matched the recording");
+    }
+}
+
+// e.g. "AJOSampleBot by John Doe vs LineDanceV2 by Matthew Menke"
+fn format_info(compilers: &[Compiler]) -> String {
+    compilers
+        .iter()
+        .map(|compiler| match &compiler.info {
+            Some(info) => info.to_string(),
+            None => "<none>".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" vs ")
+}
+
+// e.g. "Team 2 [Z-x]"
+fn format_team(team: usize, teams: usize, player_bots: u16) -> String {
+    let ids = Emulator::team_ids(team, teams, player_bots);
+    format!(
+        "Team {} [{}-{}]",
+        team + 1,
+        Bot::glyph_from_id(*ids.start()),
+        Bot::glyph_from_id(*ids.end())
+    )
+}
+
+fn format_mutated(emulator: &Emulator) -> String {
+    let mutated = emulator.mutated_bots();
+
+    if mutated.is_empty() {
+        return "none".to_string();
     }
+
+    mutated
+        .iter()
+        .map(|bot| format!("{} (x{})", bot.get_name(), bot.mutations))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
@@ -1,16 +1,21 @@
+use crate::diagnostic::{Diagnostic, Span};
 use crate::tokenizer::InstructionType;
 use crate::tokenizer::Token;
+use std::fmt::{Display, Formatter};
 
 pub struct Parser {
     position: usize,
     read_position: usize,
     token: Token,
     input: Vec<Token>,
+    spans: Vec<Span>,
+    pub token_spans: Vec<Span>, // one for every token returned by next_token
+    pub errors: Vec<Diagnostic>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ParserToken {
-    EOF,
+    Eof,
     Invalid,
     BotInfo(Vec<String>),
     Instruction(Instruction),
@@ -28,8 +33,30 @@ pub struct Instruction {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Info {
-    bot_name: String,
-    author_name: String,
+    pub bot_name: String,
+    pub author_name: String,
+}
+
+impl Info {
+    // the tokenizer splits `info: name, author` on ", ", anything after the author is ignored
+    pub fn parse(info: &[String]) -> Result<Info, String> {
+        let field = |index: usize| info.get(index).map(|field| field.trim()).unwrap_or_default();
+
+        match (field(0), field(1)) {
+            ("", _) => Err("expected `info: name, author`, the bot name is missing".to_string()),
+            (_, "") => Err("expected `info: name, author`, the author is missing".to_string()),
+            (bot_name, author_name) => Ok(Info {
+                bot_name: bot_name.to_string(),
+                author_name: author_name.to_string(),
+            }),
+        }
+    }
+}
+
+impl Display for Info {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} by {}", self.bot_name, self.author_name)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -76,7 +103,7 @@ pub enum Register {
 
 impl From<Register> for u16 {
     fn from(value: Register) -> Self {
-        return match value {
+        match value {
             Register::R0 => 0,
             Register::R1 => 1,
             Register::R2 => 2,
@@ -92,14 +119,14 @@ impl From<Register> for u16 {
             Register::R12 => 12,
             Register::R13 => 13,
             Register::SP => 15,
-        };
+        }
     }
 }
 
 
 impl From<u16> for Register {
     fn from(reg: u16) -> Self {
-        return match reg {
+        match reg {
             0 => Register::R0,
             1 => Register::R1,
             2 => Register::R2,
@@ -116,7 +143,7 @@ impl From<u16> for Register {
             13 => Register::R13,
             15 => Register::SP,
             _ => Register::R0,
-        };
+        }
     }
 }
 
@@ -142,7 +169,7 @@ impl From<Token> for Operand {
             Token::Register(reg) => {
                 Operand::Register(Register::from(reg))
             }
-            Token::StackPointer => return Operand::Register(Register::SP),
+            Token::StackPointer => Operand::Register(Register::SP),
             _ => panic!("Token is not an operand"),
         }
     }
@@ -161,12 +188,15 @@ impl From<Operand> for u16 {
 }
 
 impl Parser {
-    pub fn new(input: Vec<Token>) -> Parser {
+    pub fn new(input: Vec<Token>, spans: Vec<Span>) -> Parser {
         let mut parser = Parser {
             position: 0,
             read_position: 0,
             token: input[0].clone(),
             input,
+            spans,
+            token_spans: Vec::new(),
+            errors: Vec::new(),
         };
 
         parser.read_token();
@@ -175,7 +205,7 @@ impl Parser {
     }
 
     fn read_token(&mut self) {
-        if !(self.token == Token::EOF) {
+        if !(self.token == Token::Eof) {
             self.token = self.input[self.read_position].clone();
         }
 
@@ -187,29 +217,101 @@ impl Parser {
         self.input[self.read_position + 1].clone()
     }
 
+    fn span_at(&self, position: usize) -> Span {
+        self.spans[position.min(self.spans.len() - 1)]
+    }
+
+    fn current_span(&self) -> Span {
+        self.span_at(self.position)
+    }
+
+    fn describe(token: &Token) -> String {
+        match token {
+            Token::Eof => "end of file".to_string(),
+            Token::Invalid => "invalid token".to_string(),
+            Token::Comment => "comment".to_string(),
+            Token::BotInfo(_) => "info line".to_string(),
+            Token::Ident(ident) => format!("`{ident}`"),
+            Token::Number(number) => format!("`{number}`"),
+            Token::Register(reg) => format!("`r{reg}`"),
+            Token::StackPointer => "`sp`".to_string(),
+            Token::Instruction(instruction) => format!("instruction `{}`", String::from(instruction.clone())),
+            Token::Comma => "`,`".to_string(),
+            Token::Colon => "`:`".to_string(),
+            Token::OpenBracket => "`[`".to_string(),
+            Token::CloseBracket => "`]`".to_string(),
+            Token::OpenCurly => "`{`".to_string(),
+            Token::CloseCurly => "`}`".to_string(),
+            Token::Plus => "`+`".to_string(),
+            Token::Minus => "`-`".to_string(),
+        }
+    }
+
+    fn error_expected(&mut self, expected: &str) {
+        // invalid tokens were already reported by the tokenizer
+        if self.token == Token::Invalid {
+            return;
+        }
+
+        let message = format!("expected {}, found {}", expected, Self::describe(&self.token));
+        self.errors.push(Diagnostic::new(message, self.current_span()));
+    }
+
+    // skips the rest of the line, so one mistake doesn't cascade into more errors
+    fn recover(&mut self) {
+        let line = self.current_span().line;
+
+        while self.token != Token::Eof
+            && self.read_position < self.input.len()
+            && self.span_at(self.read_position).line == line
+        {
+            self.read_token();
+        }
+    }
+
     fn read_data(&mut self) -> ParserToken {
         let mut data: Vec<Value> = Vec::new();
+        let data_span = self.current_span();
         self.read_token();
+
+        if self.token != Token::OpenCurly {
+            self.error_expected("`{` after `data`");
+            return ParserToken::Invalid;
+        }
+
         self.read_token();
 
         while self.token != Token::CloseCurly {
-            match Operand::from(self.token.clone()) {
-                Operand::ImmediateValue(val) => {
-                    data.push(val);
+            match self.token.clone() {
+                Token::Number(number) => data.push(Value::Number(number)),
+                Token::Ident(label) => data.push(Value::Label(label)),
+                Token::Comment => {}
+                Token::Eof => {
+                    self.errors.push(
+                        Diagnostic::new("unclosed data block", data_span).with_label("no matching `}` for this block"),
+                    );
+                    return ParserToken::Invalid;
                 }
-                _ => break,
+                _ => self.error_expected("number or label in data block"),
             }
             self.read_token();
         }
 
-        return ParserToken::Data(data);
+        ParserToken::Data(data)
     }
 
     fn read_label(&mut self) -> ParserToken {
         if let Token::Ident(label) = self.token.clone() {
+            let label_span = self.current_span();
             self.read_token();
 
             if self.token != Token::Colon {
+                let hint = match label.to_lowercase().as_str() {
+                    "getid" | "level" | "climb" => "part of the extended instruction set, pass --extended to enable it",
+                    _ => "expected an instruction, or a `:` if this is a label",
+                };
+
+                self.errors.push(Diagnostic::new(format!("unknown instruction `{label}`"), label_span).with_label(hint));
                 return ParserToken::Invalid;
             }
 
@@ -219,15 +321,23 @@ impl Parser {
         }
     }
 
-    fn read_operand(&mut self, instruction: InstructionType) -> Operand {
+    fn read_operand(&mut self, instruction: InstructionType) -> Option<Operand> {
         match self.token.clone() {
             Token::Ident(_) | Token::Number(_) | Token::Register(_) | Token::StackPointer => {
-                return Operand::from(self.token.clone());
+                Some(Operand::from(self.token.clone()))
             }
             Token::OpenBracket => {
                 self.read_token(); // first operand
 
-                let one = Operand::from(self.token.clone());
+                let one = match self.token {
+                    Token::Ident(_) | Token::Number(_) | Token::Register(_) | Token::StackPointer => {
+                        Operand::from(self.token.clone())
+                    }
+                    _ => {
+                        self.error_expected("register, label or address");
+                        return None;
+                    }
+                };
 
                 self.read_token(); // second value (could be bracket or plus/minus)
 
@@ -236,14 +346,14 @@ impl Parser {
                     match one {
                         Operand::Register(_) => {
                             // what is this, fucking lisp?
-                            return Operand::RegisterIndexedDirect(
+                            Some(Operand::RegisterIndexedDirect(
                                 Box::new(one),
                                 PlusMinus::Plus,
                                 Box::new(Operand::ImmediateValue(Value::Number(0))),
-                            );
+                            ))
                         }
-                        Operand::ImmediateValue(value) => return Operand::Direct(value),
-                        _ => return one,
+                        Operand::ImmediateValue(value) => Some(Operand::Direct(value)),
+                        _ => Some(one),
                     }
                 } else {
                     match self.token {
@@ -252,58 +362,81 @@ impl Parser {
 
                             self.read_token(); // second operand
 
-                            let two = Operand::from(self.token.clone());
+                            let two = match self.token {
+                                Token::Ident(_) | Token::Number(_) | Token::Register(_) | Token::StackPointer => {
+                                    Operand::from(self.token.clone())
+                                }
+                                _ => {
+                                    self.error_expected("offset");
+                                    return None;
+                                }
+                            };
 
                             self.read_token(); // read closing bracket
 
-                            return Operand::RegisterIndexedDirect(
+                            if self.token != Token::CloseBracket {
+                                self.error_expected("`]`");
+                                return None;
+                            }
+
+                            Some(Operand::RegisterIndexedDirect(
                                 Box::new(one),
                                 sign,
                                 Box::new(two),
-                            );
+                            ))
                         }
                         _ => {
-                            panic!("Sign expected for register indexed direct addressing mode");
+                            self.error_expected("`+`, `-` or `]`");
+                            None
                         }
                     }
                 }
             }
-            _ => return Operand::None,
+            _ => {
+                self.error_expected(&format!("operand for `{}`", String::from(instruction)));
+                None
+            }
         }
     }
 
     fn read_instruction_single(&mut self, instruction: InstructionType) -> ParserToken {
         self.read_token();
 
-        match self.token.clone() {
-            Token::OpenBracket => {
-                let instruction = Instruction {
-                    instruction_type: instruction.clone(),
-                    operand1: self.read_operand(instruction.clone()),
-                    operand2: Operand::None,
-                };
+        let operand1 = match self.read_operand(instruction.clone()) {
+            Some(operand) => operand,
+            None => return ParserToken::Invalid,
+        };
 
-                return ParserToken::Instruction(instruction);
-            }
-            _ => {
-                let instruction = Instruction {
-                    instruction_type: instruction.clone(),
-                    operand1: self.read_operand(instruction.clone()),
-                    operand2: Operand::None,
-                };
+        let instruction = Instruction {
+            instruction_type: instruction,
+            operand1,
+            operand2: Operand::None,
+        };
 
-                ParserToken::Instruction(instruction)
-            }
-        }
+        ParserToken::Instruction(instruction)
     }
 
     fn read_instruction_double(&mut self, instruction: InstructionType) -> ParserToken {
         self.read_token();
 
-        let op1 = self.read_operand(instruction.clone());
+        let op1 = match self.read_operand(instruction.clone()) {
+            Some(operand) => operand,
+            None => return ParserToken::Invalid,
+        };
+
         self.read_token();
+
+        if self.token != Token::Comma {
+            self.error_expected("`,` between operands");
+            return ParserToken::Invalid;
+        }
+
         self.read_token();
-        let op2 = self.read_operand(instruction.clone());
+
+        let op2 = match self.read_operand(instruction.clone()) {
+            Some(operand) => operand,
+            None => return ParserToken::Invalid,
+        };
 
         let instr = Instruction {
             instruction_type: instruction,
@@ -311,17 +444,19 @@ impl Parser {
             operand2: op2,
         };
 
-        return ParserToken::Instruction(instr);
+        ParserToken::Instruction(instr)
     }
 
     pub fn next_token(&mut self) -> ParserToken {
+        let start = self.current_span();
+
         let ptoken = match self.token.clone() {
-            Token::EOF => ParserToken::EOF,
+            Token::Eof => ParserToken::Eof,
             Token::Invalid => ParserToken::Invalid,
             Token::Instruction(instruction) => match instruction.get_operand_amount() {
                 1 => self.read_instruction_single(instruction),
                 2 => self.read_instruction_double(instruction),
-                0 | _ => {
+                _ => {
                     let instruction = Instruction {
                         instruction_type: instruction,
                         operand1: Operand::None,
@@ -336,11 +471,20 @@ impl Parser {
             },
             Token::BotInfo(info) => ParserToken::BotInfo(info),
             Token::Comment => ParserToken::Comment,
-            _ => ParserToken::Invalid,
+            _ => {
+                self.error_expected("instruction, label or data");
+                ParserToken::Invalid
+            }
         };
 
+        if ptoken == ParserToken::Invalid {
+            self.recover();
+        }
+
+        self.token_spans.push(start.to(&self.current_span()));
+
         self.read_token();
 
-        return ptoken;
+        ptoken
     }
 }
@@ -0,0 +1,114 @@
+use ruscii::keyboard::Key;
+use std::time::{Duration, Instant};
+
+/// Ticks ran per frame for every speed, None runs as many as fit into `FRAME_BUDGET`
+const SPEEDS: [Option<u32>; 5] = [Some(1), Some(10), Some(100), Some(1000), None];
+
+/// Time spent running ticks per frame at unlimited speed, leaves enough of the frame to draw it
+const FRAME_BUDGET: Duration = Duration::from_millis(12);
+
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// Pausing and speed of the visual mode, the debugger takes over while it's attached
+pub struct Playback {
+    paused: bool,
+    step: bool,
+    speed: usize,
+    to_end: bool,
+}
+
+impl Playback {
+    pub fn new() -> Playback {
+        Playback {
+            paused: false,
+            step: false,
+            speed: 0,
+            to_end: false,
+        }
+    }
+
+    // `debugging` leaves the keys the debugger uses to it
+    pub fn handle_key(&mut self, key: Key, debugging: bool) -> bool {
+        match key {
+            Key::Space if !debugging => self.paused = !self.paused,
+            Key::S if !debugging => {
+                self.paused = true;
+                self.step = true;
+            }
+            Key::Up => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            Key::Down => self.speed = self.speed.saturating_sub(1),
+            Key::End => self.to_end = true,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Whether another tick fits into this frame, `ran` ticks were ran since `started`
+    pub fn keep_running(&mut self, ran: u32, started: Instant) -> bool {
+        if self.to_end {
+            return started.elapsed() < FRAME_BUDGET;
+        }
+
+        if self.step {
+            self.step = ran == 0;
+            return ran == 0;
+        }
+
+        match (self.paused, SPEEDS[self.speed]) {
+            (true, _) => false,
+            (false, Some(ticks)) => ran < ticks,
+            (false, None) => started.elapsed() < FRAME_BUDGET,
+        }
+    }
+
+    // jumping to the end is called off by breakpoints
+    pub fn stop(&mut self) {
+        self.to_end = false;
+        self.paused = true;
+    }
+
+    pub fn is_jumping(&self) -> bool {
+        self.to_end
+    }
+
+    // e.g. "[#######.......] 250000/1000000 (25%)  Speed: 100 ticks/frame"
+    pub fn status(&self, current_tick: u32, iterations: u32) -> String {
+        // never more than a full bar, even if the tick ran past the iterations
+        let progress = (current_tick as f64 / iterations.max(1) as f64).min(1.0);
+        let filled = (progress * PROGRESS_BAR_WIDTH as f64) as usize;
+
+        let speed = match (self.to_end, SPEEDS[self.speed]) {
+            (true, _) => "jumping to the end".to_string(),
+            (false, Some(1)) => "1 tick/frame".to_string(),
+            (false, Some(ticks)) => format!("{ticks} ticks/frame"),
+            (false, None) => "unlimited".to_string(),
+        };
+
+        format!(
+            "[{}{}] {}/{} ({:.0}%)  Speed: {}{}",
+            "#".repeat(filled),
+            ".".repeat(PROGRESS_BAR_WIDTH - filled),
+            current_tick,
+            iterations,
+            progress * 100.0,
+            speed,
+            if self.paused && !self.to_end { " (paused)" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_fills_the_bar_with_the_progress() {
+        let playback = Playback::new();
+
+        assert!(playback.status(0, 100).starts_with(&format!("[{}]", ".".repeat(PROGRESS_BAR_WIDTH))));
+        assert!(playback.status(50, 100).starts_with(&format!("[{}{}]", "#".repeat(15), ".".repeat(15))));
+        assert!(playback.status(250, 100).starts_with(&format!("[{}] 250/100 (100%)", "#".repeat(30))));
+        assert!(playback.status(0, 0).starts_with(&format!("[{}] 0/0 (0%)", ".".repeat(30))));
+    }
+}
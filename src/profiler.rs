@@ -0,0 +1,198 @@
+use crate::compiler::Compiler;
+use crate::disassembler::Disassembler;
+use crate::emulator::Bot;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::Path;
+
+/// Amount of instructions listed at the top of every program's report
+const HOTTEST_INSTRUCTIONS: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counter {
+    executed: u64,
+    energy: u64,
+}
+
+/// A program as it was written, to annotate its profile with
+pub struct Source {
+    pub name: String,
+    pub program: Vec<u16>,
+    labels: HashMap<u16, Vec<String>>,
+    lines: HashMap<u16, usize>,
+}
+
+impl Source {
+    pub fn new(name: String, compiler: &Compiler) -> Source {
+        let mut labels: HashMap<u16, Vec<String>> = HashMap::new();
+        for (label, address) in &compiler.symbol_table {
+            labels.entry(*address).or_default().push(label.clone());
+        }
+        for labels in labels.values_mut() {
+            labels.sort();
+        }
+
+        Source {
+            name,
+            program: compiler.output.clone(),
+            labels,
+            lines: compiler.source_lines.clone(),
+        }
+    }
+
+    // e.g. the built in drone firmware, which has neither labels nor lines
+    pub fn firmware(name: String, program: Vec<u16>) -> Source {
+        Source {
+            name,
+            program,
+            labels: HashMap::new(),
+            lines: HashMap::new(),
+        }
+    }
+
+    // e.g. "loop+3", the closest label at or before the address
+    fn location(&self, address: u16) -> Option<String> {
+        let (label_address, labels) = self
+            .labels
+            .iter()
+            .filter(|(label_address, _)| **label_address <= address)
+            .max_by_key(|(label_address, _)| **label_address)?;
+
+        match address - label_address {
+            0 => Some(labels[0].clone()),
+            offset => Some(format!("{}+{}", labels[0], offset)),
+        }
+    }
+}
+
+/// Executions and energy spent per address, summed up over every bot running the same program
+pub struct Profiler {
+    programs: Vec<Vec<Counter>>, // one per team, drones come last
+}
+
+impl Profiler {
+    pub fn new(teams: usize) -> Profiler {
+        Profiler {
+            programs: vec![vec![Counter::default(); 3600]; teams + 1],
+        }
+    }
+
+    // `address`, `energy` and `sleeping` are the ones from before the bot's tick, sleeping bots didn't run anything
+    pub fn count(&mut self, bot: &Bot, address: u16, energy: u16, sleeping: bool) {
+        if sleeping {
+            return;
+        }
+
+        let program = bot.team.unwrap_or(self.programs.len() - 1);
+        let counter = &mut self.programs[program][address as usize % 3600];
+        counter.executed += 1;
+        // only what the bot lost, energy gained from eating isn't spent
+        counter.energy += energy.saturating_sub(bot.energy) as u64;
+    }
+
+    /// `sources` are in the same order as the teams, followed by the drones
+    pub fn report(&self, sources: &[Source], extended: bool) -> String {
+        let mut output = String::new();
+
+        for (counters, source) in self.programs.iter().zip(sources) {
+            let executed: u64 = counters.iter().map(|counter| counter.executed).sum();
+            if executed == 0 {
+                continue;
+            }
+            let energy: u64 = counters.iter().map(|counter| counter.energy).sum();
+
+            let disassembler = Disassembler::new(source.program.clone(), extended);
+            let line = |address: u16| -> String {
+                let counter = counters[address as usize];
+                format!(
+                    "{:>7}  {:>4}  {:>10} {:>6.2}%  {:>10} {:>6.2}%  {}",
+                    format!("{:04}", address),
+                    source.lines.get(&address).map_or("-".to_string(), |line| line.to_string()),
+                    counter.executed,
+                    counter.executed as f64 * 100.0 / executed as f64,
+                    counter.energy,
+                    counter.energy as f64 * 100.0 / energy.max(1) as f64,
+                    disassembler.parse_at(address, true)
+                )
+            };
+            let header = format!(
+                "{:>7}  {:>4}  {:>10} {:>7}  {:>10} {:>7}  {}",
+                "Address", "Line", "Executed", "", "Energy", "", "Instruction"
+            );
+
+            writeln!(output, "Profile of {}", source.name).unwrap();
+            writeln!(output, "{} instructions executed, {} energy spent", executed, energy).unwrap();
+            writeln!(output).unwrap();
+
+            let addresses: Vec<u16> = (0..counters.len() as u16)
+                .filter(|address| counters[*address as usize].executed > 0)
+                .collect();
+
+            // energy first, what's executed the most usually costs just 1 each
+            let mut hottest = addresses.clone();
+            hottest.sort_by_key(|address| {
+                let counter = counters[*address as usize];
+                Reverse((counter.energy, counter.executed))
+            });
+
+            writeln!(output, "Hottest instructions:").unwrap();
+            writeln!(output, "{}", header).unwrap();
+            for address in hottest.iter().take(HOTTEST_INSTRUCTIONS) {
+                match source.location(*address) {
+                    Some(location) => writeln!(output, "{}  ({})", line(*address), location).unwrap(),
+                    None => writeln!(output, "{}", line(*address)).unwrap(),
+                }
+            }
+            writeln!(output).unwrap();
+
+            // everything that ran at least once, in program order and under its labels
+            writeln!(output, "Listing:").unwrap();
+            writeln!(output, "{}", header).unwrap();
+            let mut previous: Option<u16> = None;
+            for address in addresses {
+                // instructions take 3 words, anything further away was skipped over
+                if previous.is_some_and(|previous| address > previous + 3) {
+                    writeln!(output, "{:>7}", "...").unwrap();
+                }
+                previous = Some(address);
+
+                for label in source.labels.get(&address).into_iter().flatten() {
+                    writeln!(output, "{}:", label).unwrap();
+                }
+                writeln!(output, "{}", line(address)).unwrap();
+            }
+            writeln!(output).unwrap();
+        }
+
+        output
+    }
+
+    pub fn save(&self, path: &Path, sources: &[Source], extended: bool) -> Result<(), String> {
+        fs::write(path, self.report(sources, extended))
+            .map_err(|error| format!("couldn't write {}: {error}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Position;
+
+    #[test]
+    fn counts_what_ran_before_the_bot_fell_asleep() {
+        let mut profiler = Profiler::new(1);
+        let mut bot = Bot::new(1, Some(0), Position { x: 0, y: 0, z: 0 }, 0);
+
+        // spent its last energy on this tick, it's asleep now but still ran the instruction
+        bot.sleeping = true;
+        profiler.count(&bot, 6, 1, false);
+        // had nothing left to run with
+        profiler.count(&bot, 9, 0, true);
+
+        assert_eq!(profiler.programs[0][6].executed, 1);
+        assert_eq!(profiler.programs[0][6].energy, 1);
+        assert_eq!(profiler.programs[0][9].executed, 0);
+    }
+}
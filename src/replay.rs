@@ -0,0 +1,417 @@
+use crate::colors::TEAM_COLORS;
+use crate::emulator::{Bot, Emulator, ItemType, Position, Tank, PLAYER_BOTS};
+use crate::scenario::Scenario;
+use bincode::Options;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ruscii::app::{App, Config, State};
+use ruscii::drawing::Pencil;
+use ruscii::keyboard::{Key, KeyEvent};
+use ruscii::spatial::Vec2;
+use ruscii::terminal::{Color, Window};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::PathBuf;
+
+// replay files start with this, followed by a gzip stream of the header and one frame per tick
+const REPLAY_MAGIC: &[u8] = b"NANORGS REPLAY";
+
+// bumped whenever the header or frame layout changes
+const REPLAY_VERSION: u32 = 1;
+
+// the viewer keeps a copy of the whole state this often, so seeking never replays more than this
+const CHECKPOINT_TICKS: u32 = 1000;
+
+const SPEEDS: [u32; 4] = [1, 10, 100, 1000];
+
+// varints keep the energy change of a bot down to a single byte most ticks
+fn encoding() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// The part of a bot that can be seen in the tank, energy is tracked separately
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct BotFrame {
+    pub position: Position,
+    pub sleeping: bool,
+    pub mutations: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReplayBot {
+    pub id: u16,
+    pub team: Option<usize>,
+    pub energy: u16,
+    pub frame: BotFrame,
+}
+
+impl ReplayBot {
+    fn capture(bot: &Bot) -> ReplayBot {
+        ReplayBot {
+            id: bot.id,
+            team: bot.team,
+            energy: bot.energy,
+            frame: BotFrame {
+                position: bot.position,
+                sleeping: bot.sleeping,
+                mutations: bot.mutations,
+            },
+        }
+    }
+
+    fn get_glyph(&self) -> char {
+        match (self.frame.sleeping, self.id) {
+            (false, id) => Bot::glyph_from_id(id),
+            (true, 1..=PLAYER_BOTS) => '.',
+            (true, _) => ',',
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    scenario: Scenario,
+    seed: u32,
+    programs: Vec<Vec<u16>>, // bytecode of every team, as it was loaded
+    drone_program: Vec<u16>,
+    names: Vec<String>, // one for every team
+    iterations: u32,
+    start_tick: u32, // not 0 if the run was resumed from a snapshot
+    tank: Tank,
+    bots: Vec<ReplayBot>,
+}
+
+/// Everything that changed during a single tick
+#[derive(Serialize, Deserialize, Default)]
+struct Frame {
+    energy: Vec<i32>, // change in energy of every bot
+    bots: Vec<(u16, BotFrame)>, // index of every bot that moved, fell asleep, woke up or mutated
+    eaten: Vec<Position>, // sludge taken out of the tank
+    score: u64,
+    team_scores: Vec<u64>,
+}
+
+/// Writes a frame for every tick the emulator runs
+pub struct Recorder {
+    output: BufWriter<GzEncoder<File>>, // buffered in front of the encoder, frames are written a few bytes at a time
+    bots: Vec<ReplayBot>,
+    frame: Frame,
+    error: Option<String>, // the first write that failed, nothing is recorded after it
+}
+
+impl Recorder {
+    pub fn new(
+        path: &PathBuf,
+        emulator: &Emulator,
+        programs: &[Vec<u16>],
+        drone_program: &[u16],
+        names: Vec<String>,
+    ) -> Result<Recorder, String> {
+        let mut file = File::create(path).map_err(|error| format!("couldn't create {}: {error}", path.display()))?;
+        file.write_all(REPLAY_MAGIC).map_err(|error| error.to_string())?;
+
+        let bots: Vec<ReplayBot> = emulator.bots.iter().map(ReplayBot::capture).collect();
+
+        let header = Header {
+            version: REPLAY_VERSION,
+            scenario: emulator.scenario.clone(),
+            seed: emulator.rng.get_seed(),
+            programs: programs.to_vec(),
+            drone_program: drone_program.to_vec(),
+            names,
+            iterations: emulator.iterations,
+            start_tick: emulator.current_tick,
+            tank: emulator.tank.clone(),
+            bots: bots.clone(),
+        };
+
+        let mut output = BufWriter::new(GzEncoder::new(file, Compression::default()));
+        encoding().serialize_into(&mut output, &header).map_err(|error| error.to_string())?;
+
+        Ok(Recorder {
+            output,
+            bots,
+            frame: Frame::default(),
+            error: None,
+        })
+    }
+
+    pub fn eaten(&mut self, position: Position) {
+        self.frame.eaten.push(position);
+    }
+
+    pub fn end_tick(&mut self, tank: &Tank, bots: &[Bot]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        for (index, (before, bot)) in self.bots.iter_mut().zip(bots).enumerate() {
+            let now = ReplayBot::capture(bot);
+
+            self.frame.energy.push(now.energy as i32 - before.energy as i32);
+            if now.frame != before.frame {
+                self.frame.bots.push((index as u16, now.frame));
+            }
+
+            *before = now;
+        }
+
+        self.frame.score = tank.score;
+        self.frame.team_scores = tank.team_scores.clone();
+
+        let frame = std::mem::take(&mut self.frame);
+        if let Err(error) = encoding().serialize_into(&mut self.output, &frame) {
+            self.error = Some(error.to_string());
+        }
+    }
+
+    // the ticks recorded before a failed write are still written out, the replay plays up to there
+    pub fn finish(self) -> Result<(), String> {
+        let finished = self
+            .output
+            .into_inner()
+            .map_err(|error| error.to_string())
+            .and_then(|encoder| encoder.finish().map_err(|error| error.to_string()));
+
+        match self.error {
+            Some(error) => Err(error),
+            None => finished.map(|_| ()),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ReplayState {
+    tick: u32,
+    offset: usize, // where the frame of the next tick starts
+    tank: Tank,
+    bots: Vec<ReplayBot>,
+    score: u64,
+    team_scores: Vec<u64>,
+}
+
+/// A replay file read back into memory, positioned at a single tick
+pub struct Replay {
+    header: Header,
+    frames: Vec<u8>,
+    checkpoints: Vec<ReplayState>, // one every CHECKPOINT_TICKS ticks, starting at the first one
+    state: ReplayState,
+    end_tick: u32,
+}
+
+impl Replay {
+    pub fn load(path: &PathBuf) -> Result<Replay, String> {
+        let bytes = fs::read(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+
+        let compressed = bytes
+            .strip_prefix(REPLAY_MAGIC)
+            .ok_or(format!("{} is not a replay", path.display()))?;
+
+        // a replay cut short by a crash still plays up to where it ends
+        let mut data = vec![];
+        let _ = GzDecoder::new(compressed).read_to_end(&mut data);
+
+        let mut reader = &data[..];
+        let version: u32 = encoding().deserialize_from(reader).map_err(|error| error.to_string())?;
+        if version != REPLAY_VERSION {
+            return Err(format!(
+                "{} is a version {} replay, only version {} is supported",
+                path.display(),
+                version,
+                REPLAY_VERSION
+            ));
+        }
+
+        let header: Header = encoding()
+            .deserialize_from(&mut reader)
+            .map_err(|error| format!("{} is not a replay: {error}", path.display()))?;
+        let frames = reader.to_vec();
+
+        let state = ReplayState {
+            tick: header.start_tick,
+            offset: 0,
+            tank: header.tank.clone(),
+            bots: header.bots.clone(),
+            score: header.tank.score,
+            team_scores: header.tank.team_scores.clone(),
+        };
+
+        let mut replay = Replay {
+            header,
+            frames,
+            checkpoints: vec![],
+            end_tick: state.tick,
+            state,
+        };
+
+        // run through the whole thing once to know where it ends and where to seek from
+        loop {
+            if (replay.state.tick - replay.header.start_tick).is_multiple_of(CHECKPOINT_TICKS) {
+                replay.checkpoints.push(replay.state.clone());
+            }
+            if !replay.step() {
+                break;
+            }
+        }
+        replay.end_tick = replay.state.tick;
+        replay.state = replay.checkpoints[0].clone();
+
+        Ok(replay)
+    }
+
+    // advances by a tick, false once there are no frames left
+    fn step(&mut self) -> bool {
+        let mut reader = match self.frames.get(self.state.offset..) {
+            Some(reader) if !reader.is_empty() => reader,
+            _ => return false,
+        };
+
+        let frame: Frame = match encoding().deserialize_from(&mut reader) {
+            Ok(frame) => frame,
+            Err(_) => return false,
+        };
+        self.state.offset = self.frames.len() - reader.len();
+
+        let state = &mut self.state;
+        for (bot, change) in state.bots.iter_mut().zip(frame.energy) {
+            bot.energy = (bot.energy as i32 + change) as u16;
+        }
+        for (index, bot_frame) in frame.bots {
+            state.bots[index as usize].frame = bot_frame;
+        }
+        for position in frame.eaten {
+            state.tank.remove_item(&position);
+        }
+        state.score = frame.score;
+        state.team_scores = frame.team_scores;
+        state.tick += 1;
+
+        true
+    }
+
+    pub fn seek(&mut self, tick: u32) {
+        let tick = tick.clamp(self.header.start_tick, self.end_tick);
+
+        if tick < self.state.tick || tick - self.state.tick > CHECKPOINT_TICKS {
+            let checkpoint = ((tick - self.header.start_tick) / CHECKPOINT_TICKS) as usize;
+            self.state = self.checkpoints[checkpoint].clone();
+        }
+
+        while self.state.tick < tick && self.step() {}
+    }
+
+    pub fn play(mut self) {
+        let mut app = App::config(Config::fps(Config::new(), 60));
+
+        let tank_bounds = self.header.tank.bounds();
+        let mut level: u8 = 0;
+        let mut paused = false;
+        let mut speed = 0;
+
+        app.run(|app_state: &mut State, window: &mut Window| {
+            let length = self.end_tick - self.header.start_tick;
+            let jump = (length / 100).max(1);
+
+            for key_event in app_state.keyboard().last_key_events() {
+                match key_event {
+                    KeyEvent::Pressed(Key::Esc) => app_state.stop(),
+                    KeyEvent::Pressed(Key::Q) => app_state.stop(),
+                    KeyEvent::Pressed(Key::Space) => paused = !paused,
+                    KeyEvent::Pressed(Key::Up) => speed = (speed + 1).min(SPEEDS.len() - 1),
+                    KeyEvent::Pressed(Key::Down) => speed = speed.saturating_sub(1),
+                    KeyEvent::Pressed(Key::Left) => self.seek(self.state.tick.saturating_sub(jump)),
+                    KeyEvent::Pressed(Key::Right) => self.seek(self.state.tick.saturating_add(jump)),
+                    KeyEvent::Pressed(Key::Home) => self.seek(0),
+                    KeyEvent::Pressed(Key::End) => self.seek(u32::MAX),
+                    KeyEvent::Pressed(Key::S) => {
+                        paused = true;
+                        self.seek(self.state.tick + 1);
+                    }
+                    KeyEvent::Pressed(Key::Tab) if tank_bounds.z > 1 => level = (level + 1) % tank_bounds.z,
+                    _ => (),
+                }
+            }
+
+            if !paused {
+                self.seek(self.state.tick.saturating_add(SPEEDS[speed]));
+            }
+
+            let mut pencil = Pencil::new(window.canvas_mut());
+
+            for item in self.state.tank.elements.iter().flatten() {
+                if item.position.z != level {
+                    continue;
+                }
+
+                match item.item_type {
+                    ItemType::Sludge => pencil.set_foreground(Color::Grey),
+                    ItemType::CollectionPoint => pencil.set_foreground(Color::Xterm(6)),
+                    ItemType::Ramp => pencil.set_foreground(Color::DarkGrey),
+                };
+                pencil.draw_char(item.get_glyph(), item.position.into());
+            }
+
+            for bot in &self.state.bots {
+                if bot.frame.position.z != level {
+                    continue;
+                }
+
+                if bot.frame.mutations > 0 {
+                    pencil.set_foreground(Color::Xterm(5));
+                } else {
+                    match bot.team {
+                        Some(team) => pencil.set_foreground(TEAM_COLORS[team % TEAM_COLORS.len()]),
+                        None => pencil.set_foreground(Color::Xterm(1)),
+                    };
+                }
+                pencil.draw_char(bot.get_glyph(), bot.frame.position.into());
+            }
+
+            let status_line = tank_bounds.y as i32;
+
+            pencil.set_foreground(Color::White);
+            pencil.draw_text(
+                &format!(
+                    "Tick: {}/{}  Speed: {}x{}",
+                    self.state.tick,
+                    self.end_tick,
+                    SPEEDS[speed],
+                    if paused { " (paused)" } else { "" }
+                ),
+                Vec2::xy(0, status_line),
+            );
+            if tank_bounds.z > 1 {
+                pencil.draw_text(&format!("Level: {}/{}", level + 1, tank_bounds.z), Vec2::xy(40, status_line));
+            }
+            pencil.draw_text(
+                &format!("Seed: {}  Bot Info: {}", self.header.seed, self.header.names.join(" vs ")),
+                Vec2::xy(0, status_line + 1),
+            );
+            pencil.draw_text(&format!("Score: {}", self.state.score), Vec2::xy(0, status_line + 2));
+
+            if self.state.team_scores.len() > 1 {
+                let mut x = 0;
+                for (team, score) in self.state.team_scores.iter().enumerate() {
+                    let text = format!("Team {}: {}  ", team + 1, score);
+                    pencil.set_foreground(TEAM_COLORS[team % TEAM_COLORS.len()]);
+                    pencil.draw_text(&text, Vec2::xy(x, status_line + 3));
+                    x += text.len() as i32;
+                }
+            }
+
+            pencil.set_foreground(Color::DarkGrey);
+            pencil.draw_text(
+                "space: pause  s: step  up/down: speed  left/right: seek  home/end: jump  tab: level  q: quit",
+                Vec2::xy(0, status_line + 4),
+            );
+
+            // playing past the end just stops there, so it can still be looked at
+            if self.state.tick >= self.end_tick {
+                paused = true;
+            }
+        });
+    }
+}
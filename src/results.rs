@@ -0,0 +1,113 @@
+use crate::compiler::Compiler;
+use crate::emulator::{Emulator, PLAYER_BOTS};
+use crate::scenario::RngKind;
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+pub struct PositionResult {
+    pub x: u8,
+    pub y: u8,
+    pub z: u8,
+}
+
+#[derive(Serialize)]
+pub struct TeamResult {
+    pub path: String,
+    pub name: Option<String>, // from the info line, firmware files have none
+    pub author: Option<String>,
+    pub score: u64,
+    pub live_bots: usize,
+}
+
+#[derive(Serialize)]
+pub struct BotResult {
+    pub name: String,
+    pub team: Option<usize>, // None for drones
+    pub energy: u16,
+    pub position: PositionResult,
+    pub sleeping: bool,
+    pub mutations: u32,
+}
+
+/// Final state of a run, printed as the report or written out as JSON
+#[derive(Serialize)]
+pub struct Results {
+    pub seed: u32,
+    pub rng: String,
+    pub iterations: u32,
+    pub ticks: u32, // less than `iterations` if the run was stopped early
+    pub score: u64,
+    pub live_bots: usize,
+    pub live_drones: usize,
+    pub sludge_eaten: u32,
+    pub rng_divergence: Option<String>, // only when replaying a recording
+    pub teams: Vec<TeamResult>,
+    pub bots: Vec<BotResult>,
+}
+
+impl Results {
+    pub fn collect(emulator: &Emulator, paths: &[PathBuf], compilers: &[Compiler]) -> Results {
+        let live = |players: bool| {
+            emulator
+                .bots
+                .iter()
+                .filter(|bot| (bot.id <= PLAYER_BOTS) == players && !bot.sleeping)
+                .count()
+        };
+
+        let teams = paths
+            .iter()
+            .zip(compilers)
+            .enumerate()
+            .map(|(team, (path, compiler))| TeamResult {
+                path: path.display().to_string(),
+                name: compiler.info.as_ref().map(|info| info.bot_name.clone()),
+                author: compiler.info.as_ref().map(|info| info.author_name.clone()),
+                score: emulator.tank.team_scores[team],
+                live_bots: emulator.bots.iter().filter(|bot| bot.team == Some(team) && !bot.sleeping).count(),
+            })
+            .collect();
+
+        let bots = emulator
+            .bots
+            .iter()
+            .map(|bot| BotResult {
+                name: bot.get_name(),
+                team: bot.team,
+                energy: bot.energy,
+                position: PositionResult {
+                    x: bot.position.x,
+                    y: bot.position.y,
+                    z: bot.position.z,
+                },
+                sleeping: bot.sleeping,
+                mutations: bot.mutations,
+            })
+            .collect();
+
+        let rng = match (&emulator.scenario.replay_rng, emulator.scenario.rng) {
+            (Some(_), _) => "replay",
+            (None, RngKind::Legacy) => "legacy",
+            (None, RngKind::Modern) => "modern",
+        };
+
+        Results {
+            seed: emulator.rng.get_seed(),
+            rng: rng.to_string(),
+            iterations: emulator.iterations,
+            ticks: emulator.current_tick,
+            score: emulator.tank.score,
+            live_bots: live(true),
+            live_drones: live(false),
+            sludge_eaten: emulator.tank.sludge_eaten,
+            rng_divergence: emulator.rng.get_divergence(),
+            teams,
+            bots,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
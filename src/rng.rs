@@ -1,13 +1,66 @@
+use crate::scenario::{RngKind, Scenario};
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha20Rng;
 use rand_chacha::rand_core::RngCore;
 
 
 pub trait RNGSystem: Debug {
-    fn rand(&mut self, max: Option<u32>) -> u32;
+    // `caller` names what the number is drawn for, it only matters to recordings
+    fn rand(&mut self, caller: &'static str, max: Option<u32>) -> u32;
 
     fn get_seed(&self) -> u32;
+
+    /// Describes the first call that didn't match the recording being replayed
+    fn get_divergence(&self) -> Option<String> {
+        None
+    }
+
+    /// Everything needed to carry on from this point later, None if it can't be saved
+    fn save_state(&self) -> Option<RngState> {
+        None
+    }
+
+    /// Writes out whatever is still buffered at the end of a run, and reports the first write that failed
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum RngState {
+    Legacy { state: u32, initial_seed: u32 },
+    Modern { word_pos: u128, initial_seed: u32 },
+}
+
+pub fn from_state(state: &RngState) -> Box<dyn RNGSystem> {
+    match *state {
+        RngState::Legacy { state, initial_seed } => Box::new(LegacyRNG { state, initial_seed }),
+        RngState::Modern { word_pos, initial_seed } => {
+            let mut rng = ModernRNG::new(initial_seed);
+            rng.rng.set_word_pos(word_pos);
+            Box::new(rng)
+        }
+    }
+}
+
+/// Builds the RNG picked by the scenario, recording or replaying it if asked to
+pub fn from_scenario(scenario: &Scenario, seed: u32) -> Result<Box<dyn RNGSystem>, String> {
+    let rng: Box<dyn RNGSystem> = match (&scenario.replay_rng, scenario.rng) {
+        (Some(path), _) => Box::new(ReplayRNG::load(path)?),
+        (None, RngKind::Modern) => Box::new(ModernRNG::new(seed)),
+        (None, RngKind::Legacy) => Box::new(LegacyRNG::new(seed)),
+    };
+
+    match &scenario.record_rng {
+        Some(path) => Ok(Box::new(RecordingRNG::new(path, rng)?)),
+        None => Ok(rng),
+    }
 }
 
 #[derive(Debug)]
@@ -23,7 +76,7 @@ impl LegacyRNG {
 }
 
 impl RNGSystem for LegacyRNG {
-    fn rand(&mut self, max: Option<u32>) -> u32 {
+    fn rand(&mut self, _caller: &'static str, max: Option<u32>) -> u32 {
         self.state = self.state.wrapping_mul(16807) % 0x7fffffff;
         match max {
             None => {self.state}
@@ -34,6 +87,10 @@ impl RNGSystem for LegacyRNG {
     fn get_seed(&self) -> u32 {
         self.initial_seed
     }
+
+    fn save_state(&self) -> Option<RngState> {
+        Some(RngState::Legacy { state: self.state, initial_seed: self.initial_seed })
+    }
 }
 
 #[derive(Debug)]
@@ -49,7 +106,7 @@ impl ModernRNG {
 }
 
 impl RNGSystem for ModernRNG {
-    fn rand(&mut self, max: Option<u32>) -> u32 {
+    fn rand(&mut self, _caller: &'static str, max: Option<u32>) -> u32 {
         match max {
             None => {self.rng.next_u32()}
             Some(max) => {self.rng.gen_range(0..=max)}
@@ -59,4 +116,203 @@ impl RNGSystem for ModernRNG {
     fn get_seed(&self) -> u32 {
         self.initial_seed
     }
-}
\ No newline at end of file
+
+    fn save_state(&self) -> Option<RngState> {
+        Some(RngState::Modern { word_pos: self.rng.get_word_pos(), initial_seed: self.initial_seed })
+    }
+}
+
+// e.g. "RAND 3", max is "-" if unbounded
+fn format_request(caller: &str, max: Option<u32>) -> String {
+    match max {
+        Some(max) => format!("{caller} {max}"),
+        None => format!("{caller} -"),
+    }
+}
+
+/// Passes every call through to another RNG and writes it down
+#[derive(Debug)]
+pub struct RecordingRNG {
+    inner: Box<dyn RNGSystem>,
+    output: BufWriter<File>,
+    error: Option<String>, // the first write that failed, nothing is recorded after it
+}
+
+impl RecordingRNG {
+    pub fn new(path: &PathBuf, inner: Box<dyn RNGSystem>) -> Result<RecordingRNG, String> {
+        let file = File::create(path).map_err(|error| format!("couldn't create {}: {error}", path.display()))?;
+
+        let mut rng = RecordingRNG { inner, output: BufWriter::new(file), error: None };
+        writeln!(rng.output, "# OpenNANORGS rng recording, seed {}", rng.inner.get_seed())
+            .map_err(|error| error.to_string())?;
+        Ok(rng)
+    }
+}
+
+impl RNGSystem for RecordingRNG {
+    fn rand(&mut self, caller: &'static str, max: Option<u32>) -> u32 {
+        let result = self.inner.rand(caller, max);
+
+        // the run carries on without the recording, it's reported once the run is over
+        if self.error.is_none() {
+            if let Err(error) = writeln!(self.output, "{} {}", format_request(caller, max), result) {
+                self.error = Some(error.to_string());
+            }
+        }
+
+        result
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.inner.get_seed()
+    }
+
+    fn get_divergence(&self) -> Option<String> {
+        self.inner.get_divergence()
+    }
+
+    fn save_state(&self) -> Option<RngState> {
+        self.inner.save_state()
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        self.output.flush().map_err(|error| error.to_string())
+    }
+}
+
+#[derive(Debug)]
+struct RecordedCall {
+    caller: String,
+    max: Option<u32>,
+    result: u32,
+}
+
+/// Hands out the results of a recording, keeping track of the first call that doesn't match it
+#[derive(Debug)]
+pub struct ReplayRNG {
+    calls: Vec<RecordedCall>,
+    position: usize,
+    initial_seed: u32,
+    divergence: Option<String>,
+}
+
+impl ReplayRNG {
+    pub fn load(path: &PathBuf) -> Result<ReplayRNG, String> {
+        let input = fs::read_to_string(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+
+        let mut rng = ReplayRNG { calls: vec![], position: 0, initial_seed: 0, divergence: None };
+
+        for (number, line) in input.lines().enumerate() {
+            if let Some(seed) = line.strip_prefix("# OpenNANORGS rng recording, seed ") {
+                rng.initial_seed = seed.trim().parse().unwrap_or(0);
+                continue;
+            } else if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+
+            let invalid = || format!("{}:{}: expected \"caller max result\", got \"{}\"", path.display(), number + 1, line);
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [caller, max, result] = fields[..] else {
+                return Err(invalid());
+            };
+
+            let max = match max {
+                "-" => None,
+                max => Some(max.parse().map_err(|_| invalid())?),
+            };
+
+            rng.calls.push(RecordedCall {
+                caller: caller.to_string(),
+                max,
+                result: result.parse().map_err(|_| invalid())?,
+            });
+        }
+
+        Ok(rng)
+    }
+}
+
+impl RNGSystem for ReplayRNG {
+    fn rand(&mut self, caller: &'static str, max: Option<u32>) -> u32 {
+        let index = self.position;
+        self.position += 1;
+
+        let call = match self.calls.get(index) {
+            Some(call) => call,
+            None => {
+                if self.divergence.is_none() {
+                    self.divergence = Some(format!(
+                        "call {} \"{}\" is past the end of the recording",
+                        index + 1,
+                        format_request(caller, max)
+                    ));
+                }
+                return 0;
+            }
+        };
+
+        if self.divergence.is_none() && (call.caller != caller || call.max != max) {
+            self.divergence = Some(format!(
+                "call {} is \"{}\", the recording has \"{}\"",
+                index + 1,
+                format_request(caller, max),
+                format_request(&call.caller, call.max)
+            ));
+        }
+
+        // keep results in range even after the run went its own way
+        match max {
+            Some(max) if call.result > max => call.result % (max + 1),
+            _ => call.result,
+        }
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.initial_seed
+    }
+
+    // a run that stops early diverges as well, it just has nothing left to compare
+    fn get_divergence(&self) -> Option<String> {
+        if self.divergence.is_none() && self.position < self.calls.len() {
+            return Some(format!("the run ended after {} calls, the recording has {}", self.position, self.calls.len()));
+        }
+
+        self.divergence.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_replay_the_same_numbers() {
+        let path = std::env::temp_dir().join("open_nanorgs_recording.txt");
+        let mut recording = RecordingRNG::new(&path, Box::new(LegacyRNG::new(3))).unwrap();
+        let recorded: Vec<u32> = (0..100).map(|max| recording.rand("test", Some(max))).collect();
+        recording.finish().unwrap();
+
+        let mut replay = ReplayRNG::load(&path).unwrap();
+        let replayed: Vec<u32> = (0..100).map(|max| replay.rand("test", Some(max))).collect();
+        assert_eq!(replayed, recorded);
+        assert_eq!(replay.get_seed(), 3);
+        assert_eq!(replay.get_divergence(), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn failed_writes_are_reported_when_finishing() {
+        // every write to /dev/full fails, the first ones only once the buffer is flushed
+        let mut recording = RecordingRNG::new(&PathBuf::from("/dev/full"), Box::new(LegacyRNG::new(3))).unwrap();
+        let numbers: Vec<u32> = (0..10000).map(|_| recording.rand("test", Some(9))).collect();
+
+        let mut plain = LegacyRNG::new(3);
+        assert!(numbers.iter().all(|number| *number == plain.rand("test", Some(9))));
+        assert!(recording.finish().err().unwrap().contains("No space left on device"));
+    }
+}
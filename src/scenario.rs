@@ -0,0 +1,139 @@
+use crate::emulator::{Position, COLLECTION_POINTS, PLAYER_BOTS, RAMPS_PER_LEVEL};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RngKind {
+    Legacy,
+    Modern,
+}
+
+/// Tank layout and game rules, every value that's left out matches the original game
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub width: u8,
+    pub height: u8,
+    pub levels: u8,
+    pub sludge: usize, // initial amount of sludge in the tank
+    pub bots: u16,     // bots shared between all teams
+    pub drones: u16,
+    pub energy: u16, // starting energy of every bot
+    pub eat_reward: u16,
+    pub travel_cost: u16,
+    pub rng: RngKind,
+    pub record_rng: Option<PathBuf>, // file every random number drawn is written to
+    pub replay_rng: Option<PathBuf>, // recording to draw random numbers from instead
+    pub extended: bool,
+    pub compat: bool, // follow the original NANORGS executable where it's known to differ, not verified against it yet
+}
+
+impl Default for Scenario {
+    fn default() -> Scenario {
+        Scenario {
+            width: 70,
+            height: 40,
+            levels: 1,
+            sludge: 200,
+            bots: PLAYER_BOTS,
+            drones: 20,
+            energy: 10000,
+            eat_reward: 2000,
+            travel_cost: 10,
+            rng: RngKind::Legacy,
+            record_rng: None,
+            replay_rng: None,
+            extended: false,
+            compat: false,
+        }
+    }
+}
+
+impl Scenario {
+    // JSON if the file ends in .json, TOML otherwise
+    pub fn load(path: &PathBuf) -> Result<Scenario, String> {
+        let input = fs::read_to_string(path).map_err(|error| format!("couldn't read file: {error}"))?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+        let mut scenario: Scenario = if is_json {
+            serde_json::from_str(&input).map_err(|error| error.to_string())?
+        } else {
+            toml::from_str(&input).map_err(|error| error.to_string().trim_end().to_string())?
+        };
+
+        // paths are relative to the scenario file
+        if let Some(directory) = path.parent() {
+            for rng_path in [&mut scenario.record_rng, &mut scenario.replay_rng].into_iter().flatten() {
+                *rng_path = directory.join(&rng_path);
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    pub fn bounds(&self) -> Position {
+        Position::new(self.width, self.height, self.levels)
+    }
+
+    // catches anything that would make placing items and bots impossible
+    pub fn validate(&self, teams: usize) -> Result<(), String> {
+        if self.compat {
+            let original = Scenario {
+                record_rng: self.record_rng.clone(),
+                replay_rng: self.replay_rng.clone(),
+                compat: true,
+                ..Scenario::default()
+            };
+
+            if *self != original || teams > 1 {
+                return Err("compatibility mode only supports the original rules with a single bot program".to_string());
+            }
+        }
+
+        if self.width == 0 || self.height == 0 {
+            return Err("the tank needs to be at least 1x1".to_string());
+        }
+
+        if !(1..=8).contains(&self.levels) {
+            return Err(format!("levels must be between 1 and 8, got {}", self.levels));
+        }
+
+        if self.levels > 1 && !self.extended {
+            return Err("a tank with several levels needs the extended instruction set (--extended)".to_string());
+        }
+
+        if !(1..=PLAYER_BOTS).contains(&self.bots) {
+            return Err(format!("bots must be between 1 and {}, got {}", PLAYER_BOTS, self.bots));
+        }
+
+        if self.drones > 500 {
+            return Err(format!("drones must be at most 500, got {}", self.drones));
+        }
+
+        if teams > self.bots as usize {
+            return Err(format!("{} bots can't be split between {} teams", self.bots, teams));
+        }
+
+        let tiles = self.width as usize * self.height as usize * self.levels as usize;
+        let items = COLLECTION_POINTS + RAMPS_PER_LEVEL * 2 * (self.levels as usize - 1) + self.sludge;
+
+        if items > tiles {
+            return Err(format!("{} items don't fit into a tank of {} tiles", items, tiles));
+        }
+
+        if self.bots as usize + self.drones as usize > tiles {
+            return Err(format!(
+                "{} bots and drones don't fit into a tank of {} tiles",
+                self.bots + self.drones,
+                tiles
+            ));
+        }
+
+        Ok(())
+    }
+}
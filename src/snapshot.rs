@@ -0,0 +1,160 @@
+use crate::emulator::{Bot, Emulator, Tank};
+use crate::rng::{self, RngState};
+use crate::scenario::Scenario;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// snapshot files start with this, followed by the bincode encoded `Snapshot`
+const SNAPSHOT_MAGIC: &[u8] = b"NANORGS SNAPSHOT";
+
+// bumped whenever the layout below changes, old snapshots can't be read anymore then
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// The complete state of an emulator at the end of a tick
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    pub scenario: Scenario,
+    pub rng: RngState,
+    pub tank: Tank,
+    pub bots: Vec<Bot>,
+    pub current_tick: u32,
+}
+
+impl Snapshot {
+    pub fn capture(emulator: &Emulator) -> Result<Snapshot, String> {
+        let rng = emulator
+            .rng
+            .save_state()
+            .ok_or("can't take a snapshot while replaying an RNG recording")?;
+
+        // recordings are tied to the run that made them, the resumed one starts without
+        let scenario = Scenario {
+            record_rng: None,
+            replay_rng: None,
+            ..emulator.scenario.clone()
+        };
+
+        Ok(Snapshot {
+            version: SNAPSHOT_VERSION,
+            scenario,
+            rng,
+            tank: emulator.tank.clone(),
+            bots: emulator.bots.clone(),
+            current_tick: emulator.current_tick,
+        })
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<(), String> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(self).map_err(|error| error.to_string())?);
+        fs::write(path, bytes).map_err(|error| format!("couldn't write {}: {error}", path.display()))
+    }
+
+    pub fn load(path: &PathBuf) -> Result<Snapshot, String> {
+        let bytes = fs::read(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+
+        let bytes = bytes
+            .strip_prefix(SNAPSHOT_MAGIC)
+            .ok_or(format!("{} is not a snapshot", path.display()))?;
+
+        // the version comes first, check it before trying to make sense of the rest
+        let version: u32 = bincode::deserialize(bytes).map_err(|error| error.to_string())?;
+        if version != SNAPSHOT_VERSION {
+            return Err(format!(
+                "{} is a version {} snapshot, only version {} is supported",
+                path.display(),
+                version,
+                SNAPSHOT_VERSION
+            ));
+        }
+
+        bincode::deserialize(bytes).map_err(|error| format!("{} is not a snapshot: {error}", path.display()))
+    }
+
+    pub fn teams(&self) -> usize {
+        self.tank.team_scores.len()
+    }
+
+    // `iterations` counts from the start of the original run, it has to leave something to run
+    pub fn restore(self, iterations: u32) -> Result<Emulator, String> {
+        if iterations <= self.current_tick {
+            return Err(format!(
+                "the snapshot was taken at tick {}, resuming it needs more iterations than that",
+                self.current_tick
+            ));
+        }
+
+        Ok(Emulator {
+            rng: rng::from_state(&self.rng),
+            tank: self.tank,
+            bots: self.bots,
+            iterations,
+            current_tick: self.current_tick,
+            tracer: None,
+            recorder: None,
+            profiler: None,
+            scenario: self.scenario,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Compiler;
+    use crate::emulator::DRONE_FIRMWARE;
+    use crate::scenario::RngKind;
+
+    fn emulator(scenario: &Scenario, iterations: u32) -> Emulator {
+        let compiler = Compiler::load(&PathBuf::from("bots/samplebot.asm"), false, false).unwrap();
+        let rng = rng::from_scenario(scenario, 7).unwrap();
+        Emulator::new(&[compiler.output], &DRONE_FIRMWARE, scenario, iterations, rng)
+    }
+
+    fn run_to_end(emulator: &mut Emulator) {
+        while emulator.current_tick < emulator.iterations {
+            emulator.tick();
+        }
+    }
+
+    // everything a snapshot holds, encoded so it can be compared
+    fn state(emulator: &Emulator) -> Vec<u8> {
+        bincode::serialize(&Snapshot::capture(emulator).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn resumed_runs_match_uninterrupted_ones() {
+        for rng in [RngKind::Legacy, RngKind::Modern] {
+            let scenario = Scenario { rng, ..Scenario::default() };
+            let path = std::env::temp_dir().join(format!("open_nanorgs_{:?}.snapshot", rng));
+
+            let mut first = emulator(&scenario, 3000);
+            run_to_end(&mut first);
+            Snapshot::capture(&first).unwrap().save(&path).unwrap();
+
+            let loaded = Snapshot::load(&path).unwrap();
+            assert_eq!(loaded.teams(), 1);
+            let mut resumed = loaded.restore(6000).unwrap();
+            assert_eq!(state(&resumed), state(&first));
+
+            let mut uninterrupted = emulator(&scenario, 6000);
+            run_to_end(&mut uninterrupted);
+            run_to_end(&mut resumed);
+            assert_eq!(state(&resumed), state(&uninterrupted));
+        }
+    }
+
+    #[test]
+    fn rejects_other_files_and_spent_iterations() {
+        let path = std::env::temp_dir().join("open_nanorgs_not_a.snapshot");
+        fs::write(&path, b"main:\n    jmp main\n").unwrap();
+        assert!(Snapshot::load(&path).err().unwrap().ends_with("is not a snapshot"));
+
+        let mut emulator = emulator(&Scenario::default(), 100);
+        run_to_end(&mut emulator);
+        let snapshot = Snapshot::capture(&emulator).unwrap();
+        assert!(snapshot.restore(100).is_err());
+    }
+}
@@ -1,52 +1,60 @@
 use std::collections::HashMap;
+use crate::diagnostic::{Diagnostic, Span};
 use crate::parser::ParserToken;
 
 pub struct SymbolTable {
     pub label_to_address: HashMap<String, u16>,
+    label_spans: HashMap<String, Span>,
     position: u16,
+    pub errors: Vec<Diagnostic>,
 }
 
 impl SymbolTable {
-    pub fn new(ast: &Vec<ParserToken>) -> SymbolTable {
+    pub fn new(ast: &[ParserToken], spans: &[Span]) -> SymbolTable {
         let mut table = SymbolTable {
             label_to_address: HashMap::new(),
+            label_spans: HashMap::new(),
             position: 0,
+            errors: Vec::new(),
         };
 
-        table.generate(ast);
+        table.generate(ast, spans);
 
         table
     }
 
-    fn add_label(&mut self, label: &String, position: u16) {
-        if self.label_to_address.contains_key(&label.to_lowercase()) {
-            panic!("Label \"{label}\" already defined")
+    fn add_label(&mut self, label: &String, position: u16, span: Span) {
+        if let Some(first) = self.label_spans.get(&label.to_lowercase()) {
+            let first_line = first.line;
+            self.errors.push(
+                Diagnostic::new(format!("label `{label}` is already defined"), span)
+                    .with_label(format!("first defined on line {first_line}"))
+                    .with_focus(label.clone()),
+            );
         }
         else {
             self.label_to_address.insert(label.to_lowercase(), position);
+            self.label_spans.insert(label.to_lowercase(), span);
         }
     }
 
-    fn generate(&mut self, ast: &Vec<ParserToken>) {
+    fn generate(&mut self, ast: &[ParserToken], spans: &[Span]) {
         for i in 0..ast.len() {
             let node = &ast[i];
 
             match node {
                 ParserToken::Label(label) => {
-                    if self.position % 3 != 0 {
-                        match &ast[i + 1] {
-                            ParserToken::Instruction(_) => {
-                                self.position += 3 - self.position % 3
-                            }
-                            _ => {}
+                    if !self.position.is_multiple_of(3) {
+                        if let ParserToken::Instruction(_) = &ast[i + 1] {
+                            self.position += 3 - self.position % 3
                         }
                     }
 
-                    self.add_label(label, self.position);
+                    self.add_label(label, self.position, spans[i]);
                 }
                 ParserToken::Instruction(_) => {
                     // realign addresses to 3 word border
-                    if self.position % 3 != 0 {
+                    if !self.position.is_multiple_of(3) {
                         self.position += 3 - self.position % 3;
                     }
 
@@ -1,6 +1,8 @@
+use crate::diagnostic::{Diagnostic, Span};
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
-    EOF,
+    Eof,
     Invalid,
     Comment,
     BotInfo(Vec<String>),
@@ -60,6 +62,11 @@ pub enum InstructionType {
     POKE = 35,
     PEEK = 36,
     CKSUM = 37,
+
+    // Extended Instruction Set
+    GETID = 38,
+    LEVEL = 39,
+    CLIMB = 40,
 }
 
 impl From<u16> for InstructionType {
@@ -103,6 +110,9 @@ impl From<u16> for InstructionType {
             35 => InstructionType::POKE,
             36 => InstructionType::PEEK,
             37 => InstructionType::CKSUM,
+            38 => InstructionType::GETID,
+            39 => InstructionType::LEVEL,
+            40 => InstructionType::CLIMB,
             _ => panic!("Not a valid instruction!")
         }
     }
@@ -149,6 +159,9 @@ impl From<InstructionType> for String {
             InstructionType::POKE => "poke",
             InstructionType::PEEK => "peek",
             InstructionType::CKSUM => "cksum",
+            InstructionType::GETID => "getid",
+            InstructionType::LEVEL => "level",
+            InstructionType::CLIMB => "climb",
         }.to_string()
     }
 }
@@ -156,7 +169,7 @@ impl From<InstructionType> for String {
 impl InstructionType {
     pub fn get_operand_amount(&self) -> u16 {
         match self {
-            InstructionType::NOP | InstructionType::RET | InstructionType::EAT => 0,
+            InstructionType::NOP | InstructionType::RET | InstructionType::EAT | InstructionType::CLIMB => 0,
 
             InstructionType::PUSH
             | InstructionType::POP
@@ -173,7 +186,9 @@ impl InstructionType {
             | InstructionType::ENERGY
             | InstructionType::TRAVEL
             | InstructionType::RELEASE
-            | InstructionType::SENSE => 1,
+            | InstructionType::SENSE
+            | InstructionType::GETID
+            | InstructionType::LEVEL => 1,
 
             InstructionType::MOV
             | InstructionType::ADD
@@ -198,18 +213,26 @@ impl InstructionType {
     }
 
     pub fn is_positional(&self) -> bool {
-        match self {
+        matches!(
+            self,
             InstructionType::CALL
-            | InstructionType::JMP
-            | InstructionType::JL
-            | InstructionType::JLE
-            | InstructionType::JG
-            | InstructionType::JGE
-            | InstructionType::JE
-            | InstructionType::JNE
-            | InstructionType::JS
-            | InstructionType::JNS => true,
-            _ => false,
+                | InstructionType::JMP
+                | InstructionType::JL
+                | InstructionType::JLE
+                | InstructionType::JG
+                | InstructionType::JGE
+                | InstructionType::JE
+                | InstructionType::JNE
+                | InstructionType::JS
+                | InstructionType::JNS
+        )
+    }
+
+    /// Highest opcode that is decoded as an instruction, anything above is data
+    pub fn last_opcode(extended: bool) -> u16 {
+        match extended {
+            true => InstructionType::CLIMB as u16,
+            false => InstructionType::CKSUM as u16,
         }
     }
 }
@@ -220,24 +243,37 @@ pub struct Tokenizer {
     char: u8,
     input: Vec<u8>,
     preread: bool,
+    extended: bool,
+    line: usize,
+    pub spans: Vec<Span>,
+    pub errors: Vec<Diagnostic>,
 }
 
 impl Tokenizer {
-    pub fn new(input: String) -> Tokenizer {
+    // extended instructions are only recognized with `extended`, classic bots may use them as labels
+    pub fn new(input: String, extended: bool) -> Tokenizer {
         let mut tokenizer = Tokenizer {
             position: 0,
             read_position: 0,
             char: 0,
             input: input.into_bytes(),
             preread: false,
+            extended,
+            line: 1,
+            spans: Vec::new(),
+            errors: Vec::new(),
         };
 
         tokenizer.read_char();
 
-        return tokenizer;
+        tokenizer
     }
 
     pub fn read_char(&mut self) {
+        if self.char == b'\n' {
+            self.line += 1;
+        }
+
         if self.read_position >= self.input.len() {
             self.char = 0;
         } else {
@@ -251,7 +287,8 @@ impl Tokenizer {
     fn read_bot_info(&mut self) -> Token {
         let mut bot_info = Vec::new();
 
-        while !self.char.is_ascii_alphanumeric() {
+        // stop at the end of the line, an empty info line shouldn't swallow the next one
+        while !self.char.is_ascii_alphanumeric() && self.char != b'\n' && self.char != 0 {
             self.read_char();
         }
         while !self.char.is_ascii_control() {
@@ -260,7 +297,6 @@ impl Tokenizer {
         }
 
         let info = String::from_utf8_lossy(&bot_info)
-            .to_string()
             .split(", ")
             .map(String::from)
             .collect::<Vec<String>>();
@@ -269,13 +305,24 @@ impl Tokenizer {
     }
 
     fn read_comment(&mut self) -> Token {
-        while self.char != b'\n' {
+        while self.char != b'\n' && self.char != 0 {
             self.read_char();
         }
 
         Token::Comment
     }
 
+    // swallows a whole UTF-8 sequence, so it's reported as a single character
+    fn read_invalid(&mut self) -> Token {
+        self.read_char();
+        while (0x80..0xC0).contains(&self.char) {
+            self.read_char();
+        }
+        self.preread = true;
+
+        Token::Invalid
+    }
+
     fn skip_whitespace(&mut self) {
         while self.char.is_ascii_whitespace() {
             self.read_char();
@@ -285,7 +332,7 @@ impl Tokenizer {
     fn read_int(&mut self) -> Token {
         let pos = self.position;
 
-        while self.char.is_ascii_hexdigit() || (self.char.to_ascii_lowercase() == 'x' as u8) {
+        while self.char.is_ascii_hexdigit() || self.char.eq_ignore_ascii_case(&b'x') {
             self.read_char();
         }
 
@@ -293,16 +340,20 @@ impl Tokenizer {
 
         //println!("FORTNITE BALLS: {:#?}", self.char as char);
 
-        let num = String::from_utf8_lossy(&self.input[pos..self.position]).to_string();
+        let num_str = String::from_utf8_lossy(&self.input[pos..self.position]).to_string();
 
         // !FIXME: fuck
-        let num: Result<u16, _> = if num.starts_with("0x") {
-            u16::from_str_radix(&num.replace("0x", ""), 16)
+        let num: Result<u16, _> = if num_str.starts_with("0x") {
+            u16::from_str_radix(&num_str.replace("0x", ""), 16)
         } else {
-            num.parse()
+            num_str.parse()
         };
 
         if num.is_err() {
+            self.errors.push(
+                Diagnostic::new(format!("invalid number `{num_str}`"), self.span_from(pos))
+                    .with_label("numbers must be decimal or hexadecimal (0x) and fit into 16 bits"),
+            );
             return Token::Invalid;
         }
 
@@ -313,7 +364,7 @@ impl Tokenizer {
         let mut ident = Vec::new();
 
         loop {
-            if self.char.is_ascii_alphanumeric() || self.char == '_' as u8 {
+            if self.char.is_ascii_alphanumeric() || self.char == b'_' {
                 ident.push(self.char);
                 self.read_char();
             } else {
@@ -324,8 +375,35 @@ impl Tokenizer {
         String::from_utf8_lossy(&ident).to_string()
     }
 
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.position.min(self.input.len()), self.line)
+    }
+
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+
+        let start = self.position;
+        let line = self.line;
+
+        let token = self.read_token();
+
+        let span = Span::new(start.min(self.input.len()), self.position.min(self.input.len()), line);
+        self.spans.push(span);
+
+        if token == Token::Invalid && self.errors.last().is_none_or(|error| error.span != Some(span)) {
+            self.errors.push(
+                Diagnostic::new(
+                    format!("unexpected character `{}`", String::from_utf8_lossy(&self.input[span.start..span.end])),
+                    span,
+                )
+                .with_label("not valid here"),
+            );
+        }
+
+        token
+    }
+
+    fn read_token(&mut self) -> Token {
         self.preread = false;
 
         let token = match self.char {
@@ -349,12 +427,8 @@ impl Tokenizer {
                     let num_str: String = num_chars[1..].iter().collect();
                     let num = num_str.parse::<u16>();
 
-                    match num {
-                        Ok(val) => match val {
-                            0..=13 | 15 => return Token::Register(val),
-                            _ => {}
-                        },
-                        _ => {}
+                    if let Ok(val @ (0..=13 | 15)) = num {
+                        return Token::Register(val);
                     }
                 }
 
@@ -408,16 +482,19 @@ impl Tokenizer {
                     "POKE" => Token::Instruction(InstructionType::POKE),
                     "PEEK" => Token::Instruction(InstructionType::PEEK),
                     "CKSUM" => Token::Instruction(InstructionType::CKSUM),
+                    "GETID" if self.extended => Token::Instruction(InstructionType::GETID),
+                    "LEVEL" if self.extended => Token::Instruction(InstructionType::LEVEL),
+                    "CLIMB" if self.extended => Token::Instruction(InstructionType::CLIMB),
                     _ => Token::Ident(ident),
                 };
 
                 return token;
             }
-            0 => Token::EOF,
-            _ => Token::Invalid,
+            0 => Token::Eof,
+            _ => self.read_invalid(),
         };
 
-        if self.preread == false {
+        if !self.preread {
             self.read_char();
         }
 
@@ -432,8 +509,9 @@ impl Tokenizer {
         }
 
         // dirty hack, fixme?
-        if tokens.last() != Some(&Token::EOF) {
-            tokens.push(Token::EOF);
+        if tokens.last() != Some(&Token::Eof) {
+            tokens.push(Token::Eof);
+            self.spans.push(Span::new(self.input.len(), self.input.len(), self.line));
         }
 
         tokens
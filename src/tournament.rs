@@ -0,0 +1,328 @@
+use crate::batch::{self, Stats};
+use crate::compiler::Compiler;
+use crate::scenario::Scenario;
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: String, // from the info line, the file name if there is none
+    pub scores: Vec<u64>, // one for every game played
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// A bot, or a pairing of two, whose games were left out
+pub struct Failure {
+    pub path: PathBuf,
+    pub opponent: Option<PathBuf>, // head to head, the games of just this pairing were left out
+    pub reason: String,
+}
+
+pub struct Tournament {
+    pub solo: bool,
+    pub entries: Vec<Entry>, // ranked, best first
+    pub failed: Vec<Failure>,
+}
+
+/// Compiles every .asm file in `directory` and plays them all against each other, or alone if `solo`
+pub fn run(
+    directory: &Path,
+    solo: bool,
+    drone_program: &[u16],
+    scenario: &Scenario,
+    iterations: u32,
+    seeds: &[u32],
+    jobs: usize,
+) -> Result<Tournament, String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|error| format!("couldn't read {}: {}", directory.display(), error))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("asm")))
+        .collect();
+    paths.sort();
+
+    let mut entries = vec![];
+    let mut programs = vec![];
+    let mut failed = vec![];
+
+    // a bot that doesn't assemble is reported and sits the tournament out
+    for path in paths {
+        match Compiler::new_from_file(&path, false, scenario.extended) {
+            Ok(compiler) => {
+                let name = match &compiler.info {
+                    Some(info) => info.bot_name.clone(),
+                    None => path.file_stem().unwrap().to_string_lossy().to_string(),
+                };
+
+                entries.push(Entry {
+                    path,
+                    name,
+                    scores: vec![],
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                });
+                programs.push(compiler.output);
+            }
+            Err(error) => {
+                eprintln!("{}\n", error);
+                failed.push(Failure {
+                    path,
+                    opponent: None,
+                    reason: "it doesn't assemble".to_string(),
+                });
+            }
+        }
+    }
+
+    if entries.len() < if solo { 1 } else { 2 } {
+        return Err(format!("not enough bots in {} assembled to hold a tournament", directory.display()));
+    }
+
+    // a bot that crashes the emulator sits the tournament out too, head to head only that pairing does
+    if solo {
+        let mut crashed = vec![];
+        for (index, (entry, program)) in entries.iter_mut().zip(&programs).enumerate() {
+            match batch::run(std::slice::from_ref(program), drone_program, scenario, iterations, seeds, jobs) {
+                Ok(runs) => entry.scores = runs.iter().map(|run| run.score).collect(),
+                Err(reason) => crashed.push((index, reason)),
+            }
+        }
+
+        for (index, reason) in crashed.into_iter().rev() {
+            let entry = entries.remove(index);
+            failed.push(Failure {
+                path: entry.path,
+                opponent: None,
+                reason,
+            });
+        }
+        failed.sort_by(|a, b| a.path.cmp(&b.path));
+    } else {
+        for first in 0..entries.len() {
+            for second in first + 1..entries.len() {
+                // each side of the tank once, a game is both of them on the same seed added up
+                let pair = [programs[first].clone(), programs[second].clone()];
+                let swapped = [programs[second].clone(), programs[first].clone()];
+                let played = batch::run(&pair, drone_program, scenario, iterations, seeds, jobs).and_then(|runs| {
+                    batch::run(&swapped, drone_program, scenario, iterations, seeds, jobs)
+                        .map(|swapped_runs| (runs, swapped_runs))
+                });
+                let (runs, swapped_runs) = match played {
+                    Ok(played) => played,
+                    Err(reason) => {
+                        failed.push(Failure {
+                            path: entries[first].path.clone(),
+                            opponent: Some(entries[second].path.clone()),
+                            reason,
+                        });
+                        continue;
+                    }
+                };
+
+                for (run, swapped_run) in runs.iter().zip(&swapped_runs) {
+                    let first_score = run.team_scores[0] + swapped_run.team_scores[1];
+                    let second_score = run.team_scores[1] + swapped_run.team_scores[0];
+                    entries[first].scores.push(first_score);
+                    entries[second].scores.push(second_score);
+
+                    match first_score.cmp(&second_score) {
+                        Ordering::Greater => {
+                            entries[first].wins += 1;
+                            entries[second].losses += 1;
+                        }
+                        Ordering::Less => {
+                            entries[first].losses += 1;
+                            entries[second].wins += 1;
+                        }
+                        Ordering::Equal => {
+                            entries[first].draws += 1;
+                            entries[second].draws += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // head to head goes by wins, then draws, the mean score breaks any ties left
+    entries.sort_by(|a, b| {
+        (b.wins, b.draws)
+            .cmp(&(a.wins, a.draws))
+            .then(b.stats().mean.total_cmp(&a.stats().mean))
+    });
+
+    Ok(Tournament { solo, entries, failed })
+}
+
+impl Entry {
+    pub fn stats(&self) -> Stats {
+        Stats::of(&self.scores.iter().map(|score| *score as f64).collect::<Vec<_>>())
+    }
+}
+
+impl Tournament {
+    fn header(&self) -> Vec<&'static str> {
+        match self.solo {
+            true => vec!["rank", "bot", "path", "games", "mean", "median", "min", "max"],
+            false => vec!["rank", "bot", "path", "games", "wins", "draws", "losses", "mean"],
+        }
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(rank, entry)| {
+                let stats = entry.stats();
+                let mut row = vec![
+                    (rank + 1).to_string(),
+                    entry.name.clone(),
+                    entry.path.display().to_string(),
+                    entry.scores.len().to_string(),
+                ];
+
+                match self.solo {
+                    true => row.extend([
+                        format!("{:.1}", stats.mean),
+                        format!("{:.1}", stats.median),
+                        stats.min.to_string(),
+                        stats.max.to_string(),
+                    ]),
+                    false => row.extend([
+                        entry.wins.to_string(),
+                        entry.draws.to_string(),
+                        entry.losses.to_string(),
+                        format!("{:.1}", stats.mean),
+                    ]),
+                }
+
+                row
+            })
+            .collect()
+    }
+
+    pub fn print_leaderboard(&self) {
+        let header = self.header();
+        let rows = self.rows();
+
+        let widths: Vec<usize> = (0..header.len())
+            .map(|column| rows.iter().map(|row| row[column].len()).chain([header[column].len()]).max().unwrap())
+            .collect();
+
+        // names and paths are left aligned, numbers right aligned
+        let format_row = |row: Vec<String>| {
+            row.iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, width))| match column {
+                    1 | 2 => format!("{:<width$}", cell),
+                    _ => format!("{:>width$}", cell),
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+        };
+
+        println!("{}", format_row(header.iter().map(|title| title.to_string()).collect()));
+        for row in rows {
+            println!("{}", format_row(row));
+        }
+
+        for failure in &self.failed {
+            match &failure.opponent {
+                Some(opponent) => println!(
+                    "skipped {} against {}, {}",
+                    failure.path.display(),
+                    opponent.display(),
+                    failure.reason
+                ),
+                None => println!("skipped {}, {}", failure.path.display(), failure.reason),
+            }
+        }
+    }
+
+    pub fn write_csv(&self, path: &PathBuf) -> Result<(), String> {
+        let mut output = self.header().join(",") + "\n";
+
+        for row in self.rows() {
+            // quote every field that could contain a comma
+            let row: Vec<String> = row
+                .into_iter()
+                .map(|cell| match cell.contains([',', '"']) {
+                    true => format!("\"{}\"", cell.replace('"', "\"\"")),
+                    false => cell,
+                })
+                .collect();
+            output += &(row.join(",") + "\n");
+        }
+
+        fs::write(path, output).map_err(|error| format!("couldn't write {}: {}", path.display(), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::DRONE_FIRMWARE;
+
+    #[test]
+    fn plays_every_pairing_from_both_sides() {
+        let directory = std::env::temp_dir().join("open_nanorgs_tournament");
+        fs::create_dir_all(&directory).unwrap();
+        // the same bot twice, only where it starts could tell them apart
+        for name in ["first.asm", "second.asm"] {
+            fs::copy("bots/samplebot.asm", directory.join(name)).unwrap();
+        }
+
+        let tournament = run(&directory, false, &DRONE_FIRMWARE, &Scenario::default(), 3000, &[1, 2, 3], 2).unwrap();
+
+        for entry in &tournament.entries {
+            assert_eq!(entry.scores.len(), 3);
+            assert_eq!((entry.wins, entry.draws, entry.losses), (0, 3, 0));
+        }
+        assert_eq!(tournament.entries[0].scores, tournament.entries[1].scores);
+    }
+
+    #[test]
+    fn leaves_out_what_crashes_the_emulator() {
+        let directory = std::env::temp_dir().join("open_nanorgs_tournament_crash");
+        fs::create_dir_all(&directory).unwrap();
+        for name in ["first.asm", "second.asm"] {
+            fs::copy("bots/samplebot.asm", directory.join(name)).unwrap();
+        }
+        fs::write(directory.join("broken.asm"), "mov r0,\n").unwrap();
+        // drone firmware that doesn't fit into memory, flashing it panics
+        let drone_program = vec![0; 3601];
+
+        for solo in [true, false] {
+            let tournament = run(&directory, solo, &drone_program, &Scenario::default(), 100, &[1, 2], 2).unwrap();
+
+            let reasons: Vec<(String, Option<String>, &str)> = tournament
+                .failed
+                .iter()
+                .map(|failure| {
+                    let name = |path: &PathBuf| path.file_name().unwrap().to_string_lossy().to_string();
+                    (name(&failure.path), failure.opponent.as_ref().map(name), failure.reason.as_str())
+                })
+                .collect();
+
+            assert_eq!(reasons[0], ("broken.asm".to_string(), None, "it doesn't assemble"));
+            assert!(reasons[1..].iter().all(|(_, _, reason)| reason.starts_with("the emulator crashed on seed 1")));
+            match solo {
+                true => {
+                    assert!(tournament.entries.is_empty());
+                    assert_eq!(reasons[1].0, "first.asm");
+                    assert_eq!(reasons[2].0, "second.asm");
+                }
+                false => {
+                    assert!(tournament.entries.iter().all(|entry| entry.scores.is_empty()));
+                    assert_eq!(reasons[1].0, "first.asm");
+                    assert_eq!(reasons[1].1.as_deref(), Some("second.asm"));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::disassembler::Disassembler;
+use crate::emulator::{Bot, Position};
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Snapshot of the parts of a bot that are reported in the trace
+pub struct BotState {
+    instruction_pointer: u16,
+    instruction: [u16; 3],
+    stack_pointer: u16,
+    registers: [u16; 14],
+    flags: String,
+    energy: u16,
+    position: Position,
+    sleeping: bool,
+}
+
+impl BotState {
+    pub fn capture(bot: &Bot) -> BotState {
+        BotState {
+            instruction_pointer: bot.instruction_pointer,
+            instruction: bot.get_instruction(),
+            stack_pointer: bot.stack_pointer,
+            registers: bot.registers,
+            flags: bot.flags.to_string(),
+            energy: bot.energy,
+            position: bot.position,
+            sleeping: bot.sleeping,
+        }
+    }
+}
+
+pub struct Tracer {
+    output: BufWriter<File>,
+    bot_id: Option<u16>,
+    extended: bool,
+    // the first write that failed, nothing is traced after it
+    error: Option<String>,
+}
+
+impl Tracer {
+    /// Traces every bot in the tank if `bot_id` is `None`
+    pub fn new(path: &PathBuf, bot_id: Option<u16>, seed: u32, extended: bool) -> Result<Tracer, String> {
+        let file = File::create(path).map_err(|error| format!("couldn't create {}: {error}", path.display()))?;
+
+        let mut tracer = Tracer {
+            output: BufWriter::new(file),
+            bot_id,
+            extended,
+            error: None,
+        };
+
+        writeln!(tracer.output, "# OpenNANORGS trace, seed {seed}").map_err(|error| error.to_string())?;
+        Ok(tracer)
+    }
+
+    pub fn is_traced(&self, bot: &Bot) -> bool {
+        match self.bot_id {
+            Some(id) => bot.id == id,
+            None => true,
+        }
+    }
+
+    // one line per executed instruction, only values that changed are listed
+    pub fn log(&mut self, tick: u32, before: &BotState, bot: &Bot) {
+        // sleeping bots don't execute anything, only log when they fall asleep
+        if bot.sleeping && before.sleeping {
+            return;
+        }
+
+        let mut line = format!("{:>7} {:<3} {:04} ", tick, bot.get_name(), before.instruction_pointer);
+
+        if bot.sleeping {
+            line += "<sleeping>";
+        } else {
+            let instruction = Disassembler::parse(before.instruction, before.instruction_pointer, true, self.extended);
+            write!(line, "{:<30}", instruction).unwrap();
+
+            let delta = bot.energy as i32 - before.energy as i32;
+            write!(line, " E:{}({:+})", bot.energy, delta).unwrap();
+
+            for (reg, (old, new)) in before.registers.iter().zip(bot.registers.iter()).enumerate() {
+                if old != new {
+                    write!(line, " r{reg}:{old}->{new}").unwrap();
+                }
+            }
+
+            if before.stack_pointer != bot.stack_pointer {
+                write!(line, " sp:{}->{}", before.stack_pointer, bot.stack_pointer).unwrap();
+            }
+
+            let flags = bot.flags.to_string();
+            if before.flags != flags {
+                write!(line, " flags:{}->{}", before.flags, flags).unwrap();
+            }
+
+            if (before.position.x, before.position.y) != (bot.position.x, bot.position.y) {
+                write!(
+                    line,
+                    " pos:{},{}->{},{}",
+                    before.position.x, before.position.y, bot.position.x, bot.position.y
+                )
+                .unwrap();
+            }
+
+            if before.position.z != bot.position.z {
+                write!(line, " level:{}->{}", before.position.z, bot.position.z).unwrap();
+            }
+        }
+
+        // the run carries on without the trace, it's reported once the run is over
+        if self.error.is_none() {
+            if let Err(error) = writeln!(self.output, "{}", line.trim_end()) {
+                self.error = Some(error.to_string());
+            }
+        }
+    }
+
+    pub fn finish(&mut self) -> Result<(), String> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        self.output.flush().map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MOV r0, 5
+    const MOV_R0_IMMEDIATE: [u16; 3] = [0x6001, 0, 5];
+
+    #[test]
+    fn lines_only_list_what_changed() {
+        let path = std::env::temp_dir().join("open_nanorgs_trace.txt");
+        let mut tracer = Tracer::new(&path, None, 7, false).unwrap();
+
+        let mut bot = Bot::new(1, Some(0), Position { x: 4, y: 2, z: 0 }, 1000);
+        bot.flash(MOV_R0_IMMEDIATE.to_vec());
+        let before = BotState::capture(&bot);
+        bot.registers[0] = 5;
+        bot.energy -= 1;
+        bot.instruction_pointer = 3;
+        tracer.log(12, &before, &bot);
+
+        let before = BotState::capture(&bot);
+        bot.sleeping = true;
+        tracer.log(13, &before, &bot);
+        // still asleep, nothing is logged
+        tracer.log(14, &BotState::capture(&bot), &bot);
+        tracer.finish().unwrap();
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            trace.lines().collect::<Vec<_>>(),
+            vec![
+                "# OpenNANORGS trace, seed 7",
+                "     12 A   0000 mov r0, 5                      E:999(-1) r0:0->5",
+                "     13 A   0003 <sleeping>",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn failed_writes_are_reported_when_finishing() {
+        // every write to /dev/full fails, the first ones only once the buffer is flushed
+        let mut tracer = Tracer::new(&PathBuf::from("/dev/full"), None, 7, false).unwrap();
+        let mut bot = Bot::new(1, Some(0), Position { x: 0, y: 0, z: 0 }, 1000);
+        bot.flash(MOV_R0_IMMEDIATE.to_vec());
+
+        for tick in 0..10000 {
+            let before = BotState::capture(&bot);
+            tracer.log(tick, &before, &bot);
+        }
+
+        assert!(tracer.finish().err().unwrap().contains("No space left on device"));
+    }
+}
@@ -0,0 +1,32 @@
+import os
+import subprocess
+import re
+
+# OpenNANORGS' own --compat scores, these only catch unintended changes to compatibility mode
+# and say nothing about matching the original, that's what truth/scores.csv is for
+seeds = range(1, 4)
+iterations = 20000
+bots = sorted(bot for bot in os.listdir("../bots") if bot.endswith(".asm"))
+
+subprocess.run(["cargo", "build", "--release"], capture_output=True)
+opennanorgs_path = "../target/release/open_nanorgs"
+
+lines = ["seed,bot,score\n"]
+
+for bot in bots:
+    for seed in seeds:
+        out = subprocess.run([opennanorgs_path, "--compat", "-q", "-i", str(iterations), "-s", str(seed), "../bots/" + bot], capture_output=True, text=True)
+
+        score = re.search(r"Final score: (\d+)", out.stdout)
+
+        if score is None:
+            print("[" + bot + ", seed: " + str(seed) + "] no score in output, skipping")
+            continue
+
+        lines.append(str(seed) + "," + bot + "," + score.group(1) + "\n")
+
+    header = "[" + bot + "]"
+    print(header + " " * (20 - len(header)) + "Recorded: " + str(len(seeds)) + " seeds")
+
+with open("compat_scores.csv", "w") as file:
+    file.write("".join(lines))
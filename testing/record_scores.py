@@ -0,0 +1,34 @@
+import sys
+import os
+import subprocess
+import re
+
+if len(sys.argv) != 2:
+    print("Please specify a path to your platforms NANORGs binary")
+    print("record_scores.py only accepts a single argument")
+    exit()
+
+nanorgs_path = sys.argv[1]
+
+seeds = range(1, 11)
+bots = sorted(bot for bot in os.listdir("../bots") if bot.endswith(".asm"))
+
+lines = ["seed,bot,score\n"]
+
+for bot in bots:
+    for seed in seeds:
+        out = subprocess.run([nanorgs_path, "-p:../bots/" + bot, "-s:" + str(seed), "-q"], capture_output=True, text=True)
+
+        score = re.search(r"score\D*(\d+)", out.stdout, re.IGNORECASE)
+
+        if score is None:
+            print("[" + bot + ", seed: " + str(seed) + "] no score in output, skipping")
+            continue
+
+        lines.append(str(seed) + "," + bot + "," + score.group(1) + "\n")
+
+    header = "[" + bot + "]"
+    print(header + " " * (20 - len(header)) + "Recorded: " + str(len(seeds)) + " seeds")
+
+with open("truth/scores.csv", "w") as file:
+    file.write("".join(lines))
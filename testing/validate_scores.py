@@ -0,0 +1,33 @@
+import os
+import subprocess
+import re
+
+if not os.path.exists("truth/scores.csv"):
+    print("truth/scores.csv is missing, record it with record_scores.py first")
+    exit(1)
+
+with open("truth/scores.csv") as f:
+    rows = [line.strip().split(",") for line in f.readlines()[1:] if line.strip()]
+
+# build once instead of letting every run go through cargo
+subprocess.run(["cargo", "build", "--release"], capture_output=True)
+opennanorgs_path = "../target/release/open_nanorgs"
+
+failing_tests = 0
+
+for seed, bot, expected in rows:
+    out = subprocess.run([opennanorgs_path, "--compat", "-q", "-s", seed, "../bots/" + bot], capture_output=True, text=True)
+
+    score = re.search(r"Final score: (\d+)", out.stdout)
+    score = score.group(1) if score is not None else "<none>"
+
+    if score != expected:
+        failing_tests += 1
+        prefix = "[" + bot + ", seed: " + seed + "]"
+        print(prefix + " " * (30 - len(prefix)) + expected + " ----- " + score)
+
+if failing_tests > 0:
+    print(str(failing_tests) + " of " + str(len(rows)) + " tests failed")
+    exit(1)
+else:
+    print("all " + str(len(rows)) + " tests passed! :)")